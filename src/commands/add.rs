@@ -1,19 +1,13 @@
-use tracing::{error, info};
+use tracing::error;
 use crate::commands::{
+    attached_tracks,
     check_msg,
-    MusicQueue,
-    pull_youtube_child,
-    process_output,
-};
-use std::{
-    io::{
-        BufRead,
-        BufReader,
-    },
-    collections::{
-        VecDeque,
-    },
+    get_player,
+    queue_tracks,
+    resolve_failed,
 };
+use crate::player::SongInfo;
+use crate::resolver::ResolverContainer;
 use serenity::{
     framework::standard::{
         CommandResult,
@@ -31,49 +25,83 @@ use serenity::{
 
 #[command]
 #[only_in(guilds)]
-async fn add(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let url = match args.single::<String>(){
-        Ok(url) => url,
-        Err(_) => {
-            check_msg(msg.channel_id.say(&ctx.http, "You need a url after the command, doofus").await);
-            return Ok(());
-        },
+async fn add(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    // everything after the command, a link or a bunch of words to search for
+    let query = args.rest().trim().to_owned();
+    let tracks = if query.is_empty(){
+        // no link means an audio file attached to the message, or the one it's replying to
+        match attached_tracks(ctx, msg).await{
+            Some(tracks) => tracks,
+            None => {
+                check_msg(msg.channel_id.say(&ctx.http, "You need a url, something to search for or an audio file, doofus").await);
+                return Ok(());
+            },
+        }
+    }else{
+        let resolvers = ctx.data.read().await.get::<ResolverContainer>().expect("Expected the resolvers set up in the main.rs file").clone();
+        match resolvers.resolve(&query, msg.author.id){
+            Ok(tracks) => tracks,
+            Err(err) => {
+                error!("Failed to resolve {}: {}", query, err);
+                check_msg(msg.channel_id.say(&ctx.http, &resolve_failed(&err)).await);
+                return Ok(());
+            },
+        }
     };
-    // Discord uses the name guild but it's the server
-    let guild = msg.guild(&ctx.cache).await.unwrap();
-    let guild_id = guild.id;
 
+    match queue_tracks(ctx, msg, tracks).await{
+        0 => check_msg(msg.channel_id.say(&ctx.http, "Nothing was added to the queue").await),
+        1 => check_msg(msg.channel_id.say(&ctx.http, "Added 1 song to the queue").await),
+        count => check_msg(msg.channel_id.say(&ctx.http, &format!("Added {} songs to the queue", count)).await),
+    }
 
-    // get the handler for the voice channel we're a part of, if we're not in a voice channel then
-    // we just keep trucking, this isn't the command to put the bot in a voice chat
-    let mut comm = pull_youtube_child(url);
-    
-    // outputs the stdout to a buffer so we can read it later
-    let mut comm_out = BufReader::new(comm.stdout.as_mut().unwrap());
-    let mut data = ctx.data.write().await;
-    let mut song_map = data.get_mut::<MusicQueue>().expect("Expected a song queue set up in the main.rs file").lock().await;
-    let queue = song_map.entry(guild_id.clone()).or_insert(VecDeque::new());
+    Ok(())
+}
 
-    loop{
-        // Clear the data, read_line appends to the string
-        let mut dat = String::new();
-        // it returns the amount of bytes read from the buffer, if it's zero than we're done
-        if comm_out.read_line(&mut dat).expect("This shouldn't fail") == 0{
-            break;
-        }
-        // Make it into a SongInfo object and add it to the queue
-        match process_output(dat.clone(), msg.channel_id){
-            Some(song) => {
-                info!("Queued song {}", song.clone().json_map.get("title").and_then(serde_json::Value::as_str).unwrap());
-                queue.push_back(song);
+#[command]
+#[only_in(guilds)]
+async fn playnext(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    // everything after the command, a link or a bunch of words to search for
+    let query = args.rest().trim().to_owned();
+    if query.is_empty(){
+        check_msg(msg.channel_id.say(&ctx.http, "You need a url or something to search for after the command, doofus").await);
+        return Ok(());
+    }
+    let resolvers = ctx.data.read().await.get::<ResolverContainer>().expect("Expected the resolvers set up in the main.rs file").clone();
+    let mut tracks = match resolvers.resolve(&query, msg.author.id){
+        Ok(tracks) => tracks,
+        Err(err) => {
+            error!("Failed to resolve {}: {}", query, err);
+            check_msg(msg.channel_id.say(&ctx.http, &resolve_failed(&err)).await);
+            return Ok(());
+        },
+    };
+
+    let player = get_player(ctx, msg.guild_id.unwrap()).await;
+    // a playlist goes on the front in the same order it came in
+    let mut count = 0;
+    while let Some(res) = tracks.next().await{
+        match res{
+            Ok(track) => {
+                let song = SongInfo::new(track, msg.channel_id);
+                if let Err(err) = player.insert(count+1, song).await{
+                    check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await);
+                    break;
+                }
+                count += 1;
             },
-            None => {
-                error!("There was a problem proccessing the json for a video");
+            Err(err) => {
+                error!("There was a problem proccessing the json for a video: {}", err);
                 check_msg(msg.channel_id.say(&ctx.http, "There was a problem processing a video in the playlist, it was not added").await);
             },
         };
     }
 
+    match count{
+        0 => check_msg(msg.channel_id.say(&ctx.http, "Nothing was added to the queue").await),
+        1 => check_msg(msg.channel_id.say(&ctx.http, "That's playing next").await),
+        count => check_msg(msg.channel_id.say(&ctx.http, &format!("Those {} songs are playing next", count)).await),
+    }
 
     Ok(())
 }
@@ -0,0 +1,114 @@
+use crate::commands::{
+    check_msg,
+    get_player,
+};
+use crate::player::{
+    FilterPreset,
+    filters::PRESETS,
+};
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        Args,
+        macros::{
+            command,
+        },
+    },
+    client::Context,
+    model::{
+        channel::Message,
+    },
+};
+
+
+#[command]
+#[only_in(guilds)]
+#[aliases("loudnorm")]
+async fn normalize(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    // on or off, nothing flips it
+    let normalize = match args.rest().trim().to_lowercase().as_str(){
+        "" => None,
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => {
+            check_msg(msg.channel_id.say(&ctx.http, "It's either on or off, doofus").await);
+            return Ok(());
+        },
+    };
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    match get_player(ctx, guild_id).await.set_normalize(normalize).await{
+        Ok(true) => check_msg(msg.channel_id.say(&ctx.http, "Normalizing is on, everything plays at about the same loudness").await),
+        Ok(false) => check_msg(msg.channel_id.say(&ctx.http, "Normalizing is off").await),
+        Err(err) => check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+async fn filter(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+    let player = get_player(ctx, guild_id).await;
+
+    let arg = args.rest().trim().to_lowercase();
+    let filter = match arg.as_str(){
+        // nothing says what's on and what there is to pick from
+        "" => {
+            let names = PRESETS.iter().map(|preset| preset.to_string()).collect::<Vec<String>>().join(", ");
+            let current = match player.status().await{
+                Ok(status) => status.filter.map_or("off".to_owned(), |filter| filter.to_string()),
+                Err(err) => {
+                    check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await);
+                    return Ok(());
+                },
+            };
+            check_msg(msg.channel_id.say(&ctx.http, &format!("The filter is {}, you can pick from {} or off", current, names)).await);
+            return Ok(());
+        },
+        "off" | "none" => None,
+        arg => match arg.parse::<FilterPreset>(){
+            Ok(filter) => Some(filter),
+            Err(_) => {
+                check_msg(msg.channel_id.say(&ctx.http, "That's not a filter I know, run the command on its own to see them, doofus").await);
+                return Ok(());
+            },
+        },
+    };
+
+    match player.set_filter(filter).await{
+        Ok(()) => match filter{
+            Some(filter) => check_msg(msg.channel_id.say(&ctx.http, &format!("The {} filter is on", filter)).await),
+            None => check_msg(msg.channel_id.say(&ctx.http, "Filters are off").await),
+        },
+        Err(err) => check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases("tempo")]
+async fn speed(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    // 1.5, 1.5x and reset all work
+    let speed = match args.rest().trim().trim_end_matches('x'){
+        "" | "reset" | "normal" => 1.0,
+        arg => match arg.parse::<f64>(){
+            Ok(speed) => speed,
+            Err(_) => {
+                check_msg(msg.channel_id.say(&ctx.http, "You need a speed like 1.25 after the command, doofus").await);
+                return Ok(());
+            },
+        },
+    };
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    match get_player(ctx, guild_id).await.set_speed(speed).await{
+        Ok(()) => check_msg(msg.channel_id.say(&ctx.http, &format!("Playing at {}x speed", speed)).await),
+        Err(err) => check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
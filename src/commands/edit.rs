@@ -0,0 +1,123 @@
+use crate::commands::{
+    check_msg,
+    get_player,
+};
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        Args,
+        macros::{
+            command,
+        },
+    },
+    client::Context,
+    model::{
+        channel::Message,
+    },
+};
+
+// "3" gives back (3, 3) and "3-7" gives back (3, 7)
+fn parse_range(arg: &str) -> Option<(usize, usize)>{
+    match arg.find('-'){
+        Some(dash) => {
+            let first = arg[..dash].trim().parse().ok()?;
+            let last = arg[dash+1..].trim().parse().ok()?;
+            Some((first, last))
+        },
+        None => {
+            let num = arg.trim().parse().ok()?;
+            Some((num, num))
+        },
+    }
+}
+
+
+#[command]
+#[only_in(guilds)]
+async fn remove(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (first, last) = match parse_range(args.rest()){
+        Some(range) => range,
+        None => {
+            check_msg(msg.channel_id.say(&ctx.http, "You need a number or a range like 3-7 after the command, doofus").await);
+            return Ok(());
+        },
+    };
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    match get_player(ctx, guild_id).await.remove(first, last).await{
+        Ok(removed) if removed.len() == 1 => check_msg(msg.channel_id.say(&ctx.http, &format!("Removed {}", removed[0].track.title)).await),
+        Ok(removed) => check_msg(msg.channel_id.say(&ctx.http, &format!("Removed {} songs", removed.len())).await),
+        Err(err) => check_msg(msg.reply(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
+
+#[command("move")]
+#[only_in(guilds)]
+async fn move_song(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (from, to) = match (args.single::<usize>(), args.single::<usize>()){
+        (Ok(from), Ok(to)) => (from, to),
+        _ => {
+            check_msg(msg.channel_id.say(&ctx.http, "You need where the song is and where it's going after the command, doofus").await);
+            return Ok(());
+        },
+    };
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    match get_player(ctx, guild_id).await.move_song(from, to).await{
+        Ok(song) => check_msg(msg.channel_id.say(&ctx.http, &format!("Moved {} to {}", song.track.title, to)).await),
+        Err(err) => check_msg(msg.reply(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+async fn swap(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (first, second) = match (args.single::<usize>(), args.single::<usize>()){
+        (Ok(first), Ok(second)) => (first, second),
+        _ => {
+            check_msg(msg.channel_id.say(&ctx.http, "You need the two songs to swap after the command, doofus").await);
+            return Ok(());
+        },
+    };
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    match get_player(ctx, guild_id).await.swap(first, second).await{
+        Ok((first_song, second_song)) => check_msg(msg.channel_id.say(&ctx.http, &format!("Swapped {} and {}", second_song.track.title, first_song.track.title)).await),
+        Err(err) => check_msg(msg.reply(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+async fn clear(ctx: &Context, msg: &Message) -> CommandResult {
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    // unlike stop this leaves the song that's playing alone
+    match get_player(ctx, guild_id).await.clear().await{
+        Ok(0) => check_msg(msg.channel_id.say(&ctx.http, "The queue is already empty").await),
+        Ok(_) => check_msg(msg.channel_id.say(&ctx.http, "The queue has been purged of filth").await),
+        Err(err) => check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn ranges(){
+        assert_eq!(parse_range("3"), Some((3, 3)));
+        assert_eq!(parse_range("3-7"), Some((3, 7)));
+        assert_eq!(parse_range(" 3 - 7 "), Some((3, 7)));
+        assert_eq!(parse_range("3-"), None);
+        assert_eq!(parse_range("three"), None);
+    }
+}
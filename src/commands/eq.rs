@@ -0,0 +1,96 @@
+use crate::commands::{
+    check_msg,
+    get_player,
+};
+use crate::player::{
+    EqChange,
+    EqGains,
+    EqState,
+    filters::EQ_BANDS,
+};
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        Args,
+        macros::{
+            command,
+        },
+    },
+    client::Context,
+    model::{
+        channel::Message,
+    },
+};
+
+const USAGE: &str = "Try !eq <band> <dB>, !eq set <10 numbers>, !eq reset, or !eq save/load/delete <name>";
+
+// 1000 turns into 1k so the bands line up
+fn band_name(freq: u32) -> String{
+    if freq >= 1000{
+        format!("{}k", freq / 1000)
+    }else{
+        freq.to_string()
+    }
+}
+
+// One line per band with a little bar so you can see the shape of it
+fn show_eq(state: &EqState) -> String{
+    let mut lines = EQ_BANDS.iter().zip(state.gains.iter()).enumerate().map(|(num, (freq, gain))| {
+        let bar = "█".repeat(gain.abs().round() as usize);
+        let bar = if *gain < 0.0 { format!("-{}", bar) } else { format!("+{}", bar) };
+        format!("{:>2}. {:>4}Hz {:>+6.1}dB {}", num+1, band_name(*freq), gain, bar)
+    }).collect::<Vec<String>>();
+    if !state.profiles.is_empty(){
+        lines.push(String::new());
+        lines.push(format!("Saved: {}", state.profiles.join(", ")));
+    }
+    format!("```\n{}\n```", lines.join("\n"))
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases("equalizer")]
+async fn eq(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let first = args.single::<String>().unwrap_or_default().to_lowercase();
+    let rest = args.rest().trim().to_owned();
+    let change = match first.as_str(){
+        "" | "show" => EqChange::Show,
+        "reset" | "off" | "flat" => EqChange::Reset,
+        "set" => {
+            let gains = rest.split_whitespace().map(|gain| gain.parse::<f64>()).collect::<Result<Vec<f64>, _>>();
+            match gains{
+                Ok(gains) if gains.len() == EQ_BANDS.len() => {
+                    let mut all: EqGains = [0.0; 10];
+                    all.copy_from_slice(&gains);
+                    EqChange::All(all)
+                },
+                _ => {
+                    check_msg(msg.channel_id.say(&ctx.http, &format!("You need {} numbers after set, one for each band, doofus", EQ_BANDS.len())).await);
+                    return Ok(());
+                },
+            }
+        },
+        "save" | "load" | "delete" if rest.is_empty() => {
+            check_msg(msg.channel_id.say(&ctx.http, "You need a name for the profile, doofus").await);
+            return Ok(());
+        },
+        "save" => EqChange::Save(rest),
+        "load" => EqChange::Load(rest),
+        "delete" => EqChange::Delete(rest),
+        band => match (band.parse::<usize>(), rest.parse::<f64>()){
+            (Ok(band), Ok(gain)) => EqChange::Band(band, gain),
+            _ => {
+                check_msg(msg.channel_id.say(&ctx.http, USAGE).await);
+                return Ok(());
+            },
+        },
+    };
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    match get_player(ctx, guild_id).await.eq(change).await{
+        Ok(state) => check_msg(msg.channel_id.say(&ctx.http, &show_eq(&state)).await),
+        Err(err) => check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use crate::commands::{
+    check_msg,
+    get_player,
+    truncate,
+};
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        macros::{
+            command,
+        },
+    },
+    client::Context,
+    model::{
+        channel::Message,
+    },
+};
+
+
+#[command]
+#[only_in(guilds)]
+#[aliases("back")]
+async fn previous(ctx: &Context, msg: &Message) -> CommandResult {
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    // the player says what's playing on its own
+    if let Err(err) = get_player(ctx, guild_id).await.previous().await{
+        check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await);
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+async fn replay(ctx: &Context, msg: &Message) -> CommandResult {
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    if let Err(err) = get_player(ctx, guild_id).await.replay().await{
+        check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await);
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+async fn history(ctx: &Context, msg: &Message) -> CommandResult {
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    let history = get_player(ctx, guild_id).await.history().await.unwrap_or_default();
+    if history.is_empty(){
+        check_msg(msg.channel_id.say(&ctx.http, "Nothing has played yet").await);
+        return Ok(());
+    }
+
+    check_msg(msg.channel_id.send_message(&ctx.http, |m| {
+        m.embed(|e| {
+            e.title("Recently Played");
+            let lines = history.iter().enumerate().map(|(num, song)| {
+                let length = song.track.length_text();
+                format!("**{}.** {} `{}` <@{}>", num+1, truncate(&song.track.title, 80), length, song.track.requester.0)
+            }).collect::<Vec<String>>();
+            e.description(lines.join("\n"));
+            e.footer(|f| f.text("Newest first, !previous goes back to 1"));
+            e
+        });
+
+        m
+    }).await);
+
+    Ok(())
+}
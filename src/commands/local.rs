@@ -0,0 +1,64 @@
+use tracing::error;
+use crate::commands::{
+    check_msg,
+    play::play_tracks,
+};
+use crate::resolver::{
+    LocalLibraryContainer,
+    ResolveError,
+};
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        Args,
+        macros::{
+            command,
+        },
+    },
+    client::Context,
+    model::{
+        channel::Message,
+    },
+};
+
+
+// !local some/folder/song.mp3 plays that file, anything that isn't a file gets searched for in the
+// library's tags instead
+#[command]
+#[only_in(guilds)]
+async fn local(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let query = args.rest().trim().to_owned();
+    if query.is_empty(){
+        check_msg(msg.channel_id.say(&ctx.http, "You need a file or something to search for after the command, doofus").await);
+        return Ok(());
+    }
+    let library = match ctx.data.read().await.get::<LocalLibraryContainer>(){
+        Some(library) => library.clone(),
+        None => {
+            check_msg(msg.channel_id.say(&ctx.http, "There's no local music library set up").await);
+            return Ok(());
+        },
+    };
+
+    let tracks = match library.resolve(&query, msg.author.id){
+        Ok(tracks) => tracks,
+        Err(ResolveError::OutsideLibrary(_)) => {
+            check_msg(msg.channel_id.say(&ctx.http, "That's outside the music library, nice try").await);
+            return Ok(());
+        },
+        // not a file, see if the tags know it
+        Err(ResolveError::NotFound(_)) => match library.search(&query, 1).pop(){
+            Some(entry) => library.tracks(vec![entry], msg.author.id),
+            None => {
+                check_msg(msg.channel_id.say(&ctx.http, "Couldn't find anything in the library for that").await);
+                return Ok(());
+            },
+        },
+        Err(err) => {
+            error!("Failed to play {} from the local library: {}", query, err);
+            check_msg(msg.channel_id.say(&ctx.http, "Couldn't play that from the library, go yell at Brandon").await);
+            return Ok(());
+        },
+    };
+    play_tracks(ctx, msg, tracks).await
+}
@@ -1,164 +1,165 @@
-use std::{
-    process::{
-        Command,
-        Stdio,
-        Child,
-    },
-    time::{
-        Instant,
-    },
-    collections::{
-        VecDeque,
-        HashMap,
-    },
-    sync::{
-        Arc,
-    },
-};
-
 use serenity::{
+    client::Context,
     model::{
-        id::{
-            ChannelId,
-            GuildId,
-        },
+        id::GuildId,
         channel::Message,
     },
-    prelude::*,
     Result as SerenityResult,
 };
 
-use serde_json::{
-    Value,
-    Map as JsonMap,
-    Result as JsonResult,
-};
-use songbird::{
-    input::{
-        Codec,
-        Container,
-        Metadata,
-        Input,
-        child::{
-            children_to_reader,
-        },
+use tracing::{error, info};
+
+use crate::{
+    config::ConfigContainer,
+    player::{
+        PlayerContainer,
+        PlayerHandle,
+        SongInfo,
+    },
+    resolver::{
+        ResolveError,
+        TrackStream,
+        attachment::message_tracks,
     },
 };
 
-
-use tracing::error;
-
 pub mod play;
 pub mod skip;
 pub mod add;
 pub mod pause;
 pub mod stop;
 pub mod queue;
+pub mod search;
+pub mod edit;
+pub mod modes;
+pub mod nowplaying;
+pub mod history;
+pub mod seek;
+pub mod volume;
+pub mod audio;
+pub mod eq;
+pub mod radio;
+pub mod local;
 
-pub struct MusicQueue;
-
-impl TypeMapKey for MusicQueue{
-    //Arc is the automatic reference counter that makes the memory safe in a rust env
-    //Mutex allows access across threads with locking
-    //HashMap has a double sided queue for each server that this bot is in
-    //VecDeque is the double sided queue that allows us to push songs to the top and get the next
-    //song from the bottom
-    //SongInfo is the struct from the commands file
-    type Value = Arc<Mutex<HashMap<GuildId, VecDeque<SongInfo>>>>;
+// Every guild has its own player that owns the queue and the song that's playing, this gets it
+pub async fn get_player(ctx: &Context, guild_id: GuildId) -> PlayerHandle{
+    let players = ctx.data.read().await.get::<PlayerContainer>().expect("Expected the players set up in the main.rs file").clone();
+    players.get(guild_id).await
 }
 
-pub struct CurrentSong;
+// Gets the guild's player hooked up to a voice channel, joining the author's voice channel if we
+// aren't in one yet. Lets the author know and returns None if that can't happen
+pub async fn join_voice(ctx: &Context, msg: &Message) -> Option<PlayerHandle>{
+    // Discord uses the name guild but it's the server
+    let guild = msg.guild(&ctx.cache).await?;
+    let guild_id = guild.id;
 
-impl TypeMapKey for CurrentSong{
-    //Arc is the automatic reference counter that makes the memory safe in a rust env
-    //Mutex allows access across threads with locking
-    //HashMap has a tuple for each server that this bot is in
-    //Option<Instant> is the moment the song started playing, None means the song is paused
-    //SongInfo is the struct from the commands file
-    type Value = Arc<Mutex<HashMap<GuildId, (Option<Instant>, SongInfo)>>>;
-}
+    let channel_id = guild
+        .voice_states.get(&msg.author.id)
+        .and_then(|voice_state| voice_state.channel_id);
 
-#[derive(Clone)]
-pub struct SongInfo{
-    pub json_map: JsonMap<String, Value>,
-    pub channel: ChannelId,
-}
+    // get the voice channel ID
+    let connect_to = match channel_id{
+        Some(channel) => channel,
+        None => {
+            check_msg(msg.reply(ctx, "You need to be in a voice channel").await);
+            return None;
+        },
+    };
+
+    let manager = songbird::get(ctx).await
+        .expect("Songbird voice client was not initialized at serenity start up").clone();
 
-pub fn process_output(data: String, chan: ChannelId) -> Option<SongInfo>{
-    // The input should be a raw json object in text
-    let res: JsonResult<Value> = serde_json::from_str(&data);
-    let json_map = match res{
-        Ok(v) => {
-            // return it as a map with the signature SerdeMap<String, Value>
-            v.as_object()?.clone()
+    // get the handler for the voice channel we're a part of, if we're not in a voice channel then
+    // we try to join the one the author of message is a part of. The handler sticks around after
+    // !stop leaves so it has to actually be connected to count
+    let existing = match manager.get(guild_id){
+        Some(handler) => {
+            let connected = handler.lock().await.current_channel().is_some();
+            if connected { Some(handler) } else { None }
         },
-        Err(err) => {
-            // Shit's gone sideways
-            error!("Error parsing youtube json: {:?}", err);
-            return None
+        None => None,
+    };
+    let handler_lock = match existing{
+        Some(handler) => handler,
+        None => {
+            let res = manager.join(guild_id, connect_to).await;
+            match res.1{
+                Ok(_) => {
+                    res.0
+                },
+                Err(err) => {
+                    error!("Failed to join voice channel: {:?}", err);
+                    check_msg(msg.channel_id.say(&ctx.http, "Unable to join voice channel, go yell at Brandon").await);
+                    return None;
+                },
+            }
         },
     };
-    // Slap that json_map in there, it's got all the info we could ever want on the song
-    // I've added the channel so that everyone knows where to send messages too 
-    Some(SongInfo{
-        json_map: json_map,
-        channel: chan,
-    })
+
+    let mut handler = handler_lock.lock().await;
+    if !handler.is_deaf(){
+        if let Err(err) = handler.deafen(true).await {
+            error!("Deafen failed: {:?}", err);
+        };
+    }
+    drop(handler);
+
+    let player = get_player(ctx, guild_id).await;
+    player.attach(handler_lock).await;
+    Some(player)
 }
 
-pub fn make_source(data: &SongInfo) -> Option<Input>{
-    // This actually runs in the background and feeds data to the websocket, that's pretty cool
-    let ffmpeg = Command::new("ffmpeg")
-        .arg("-i")
-        .arg(data.json_map.get("url").and_then(serde_json::Value::as_str)?) //this is the only point of failure
-        .args(&[
-            "-loglevel",
-            "quiet",
-            "-hide_banner",
-            "-f",
-            "s16le",// THIS IS AN L NOT A 1, THIS FUCKING FONT 
-            "-ac", 
-            "2",
-            "-ar",
-            "48000",
-            "-acodec",
-            "pcm_f32le", // this if f32 little edian because that's what songbird needs it to be
-            "-",
-        ])
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .spawn()
-        .unwrap();
-    let metadata = Metadata::from_ytdl_output(Value::Object(data.json_map.clone()));
-
-    Some(Input::new(
-            true, // It's stereo
-            children_to_reader::<f32>(vec![ffmpeg]), // This is the actual data from the ffmpeg program running in the background
-            Codec::FloatPcm, //this is the codec we put in the up above
-            Container::Raw, // IT'S FOOKIN RAW
-            Some(metadata), // metadata taken from the youtube json object
-    ))
+// The audio files attached to the message or the one it replies to, None if there aren't any
+pub async fn attached_tracks(ctx: &Context, msg: &Message) -> Option<TrackStream>{
+    let config = ctx.data.read().await.get::<ConfigContainer>().expect("Expected the config set up in the main.rs file").clone();
+    message_tracks(&config.ffprobe, msg).await
 }
 
-pub fn pull_youtube_child(url: String) -> Child{
-    Command::new("youtube-dl")
-        .args(&[
-            "-f",
-            "webm[abr>0]/bestaudio/best",
-            "--print-json",
-            "--skip-download",
-            "-u",
-            "brandon.haley94@gmail.com",
-            "-p",
-            "H6A3l5e5yb1!!",
-            //"--newline",
-            &url,
-        ])
-        .stdin(Stdio::null())
-        .stderr(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .unwrap()
+// Puts the rest of what the resolver found on the end of the guild's queue as it comes in, so a big
+// playlist doesn't hold anything else up. Gives back how many songs made it in
+pub async fn queue_tracks(ctx: &Context, msg: &Message, mut tracks: TrackStream) -> usize{
+    let player = get_player(ctx, msg.guild_id.unwrap()).await;
+    let mut count = 0;
+    while let Some(res) = tracks.next().await{
+        match res{
+            Ok(track) => {
+                info!("Queued song {}", track.title);
+                let song = SongInfo::new(track, msg.channel_id);
+                if let Err(err) = player.enqueue(song).await{
+                    error!("Failed to queue a song: {}", err);
+                    check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await);
+                    break;
+                }
+                count += 1;
+            },
+            Err(err) => {
+                error!("There was a problem proccessing the json for a video: {}", err);
+                check_msg(msg.channel_id.say(&ctx.http, "There was a problem processing a video in the playlist, it was not added").await);
+            },
+        };
+    }
+    count
+}
+
+// Cuts a string down to at most max characters, with dots on the end if anything got cut, so long
+// titles don't blow through discord's embed limits
+pub fn truncate(text: &str, max: usize) -> String{
+    if text.chars().count() <= max{
+        return text.to_owned();
+    }
+    let mut short = text.chars().take(max.saturating_sub(3)).collect::<String>();
+    short.push_str("...");
+    short
+}
+
+// What to tell someone when their link didn't work out. When youtube-dl or whatever else got run
+// said what went wrong, or couldn't be run at all, that's more use than a guess
+pub fn resolve_failed(err: &ResolveError) -> String{
+    match err{
+        ResolveError::Process(_) | ResolveError::Spawn(_) => format!("Failed to process the link: {}", err),
+        _ => "Failed to process the link, perhaps it was an unsupported link".to_owned(),
+    }
 }
 
 pub fn check_msg(result: SerenityResult<Message>){
@@ -0,0 +1,110 @@
+use crate::commands::{
+    check_msg,
+    get_player,
+};
+use crate::player::{
+    LoopMode,
+    Transition,
+};
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        Args,
+        macros::{
+            command,
+        },
+    },
+    client::Context,
+    model::{
+        channel::Message,
+    },
+};
+
+
+#[command]
+#[only_in(guilds)]
+async fn shuffle(ctx: &Context, msg: &Message) -> CommandResult {
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    match get_player(ctx, guild_id).await.shuffle().await{
+        Ok(0) => check_msg(msg.channel_id.say(&ctx.http, "The queue is empty").await),
+        Ok(count) => check_msg(msg.channel_id.say(&ctx.http, &format!("Shuffled {} songs", count)).await),
+        Err(err) => check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+async fn shufflemode(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    // on or off, nothing flips it
+    let shuffle = match args.rest().trim().to_lowercase().as_str(){
+        "" => None,
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => {
+            check_msg(msg.channel_id.say(&ctx.http, "It's either on or off, doofus").await);
+            return Ok(());
+        },
+    };
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    match get_player(ctx, guild_id).await.set_shuffle(shuffle).await{
+        Ok(true) => check_msg(msg.channel_id.say(&ctx.http, "Shuffle is on, songs get picked at random").await),
+        Ok(false) => check_msg(msg.channel_id.say(&ctx.http, "Shuffle is off").await),
+        Err(err) => check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
+
+#[command("loop")]
+#[only_in(guilds)]
+async fn loop_mode(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    // off, track or queue, nothing moves on to the next one
+    let mode = match args.rest().trim(){
+        "" => None,
+        arg => match arg.parse::<LoopMode>(){
+            Ok(mode) => Some(mode),
+            Err(_) => {
+                check_msg(msg.channel_id.say(&ctx.http, "It's either off, track or queue, doofus").await);
+                return Ok(());
+            },
+        },
+    };
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    match get_player(ctx, guild_id).await.set_loop(mode).await{
+        Ok(mode) => check_msg(msg.channel_id.say(&ctx.http, &format!("Looping is {}", mode)).await),
+        Err(err) => check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+async fn transition(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    // gapless, gap <seconds> or crossfade <seconds>, nothing says what it's set to
+    let mode = args.single::<String>().unwrap_or_default().to_lowercase();
+    let secs = args.single::<u64>().ok();
+    let transition = match (mode.as_str(), secs){
+        ("", _) => None,
+        ("gapless" | "none", _) => Some(Transition::Gapless),
+        ("gap", Some(secs)) => Some(Transition::Gap(secs)),
+        ("crossfade" | "fade", Some(secs)) if secs > 0 => Some(Transition::Crossfade(secs)),
+        _ => {
+            check_msg(msg.channel_id.say(&ctx.http, "It's either gapless, gap <seconds> or crossfade <seconds>, doofus").await);
+            return Ok(());
+        },
+    };
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    match get_player(ctx, guild_id).await.set_transition(transition).await{
+        Ok(now) => check_msg(msg.channel_id.say(&ctx.http, &format!("Songs change over with {}", now)).await),
+        Err(err) => check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
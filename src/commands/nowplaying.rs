@@ -0,0 +1,31 @@
+use crate::commands::{
+    check_msg,
+    get_player,
+};
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        macros::{
+            command,
+        },
+    },
+    client::Context,
+    model::{
+        channel::Message,
+    },
+};
+
+
+#[command]
+#[only_in(guilds)]
+#[aliases("np")]
+async fn nowplaying(ctx: &Context, msg: &Message) -> CommandResult {
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    // the player posts it itself so it can keep it up to date
+    if let Err(err) = get_player(ctx, guild_id).await.show_now_playing(msg.channel_id).await{
+        check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await);
+    }
+    Ok(())
+}
@@ -1,8 +1,21 @@
+use tracing::error;
 use crate::commands::{
     check_msg,
-    MusicQueue,
+    get_player,
+    truncate,
+};
+use crate::player::{
+    PlayerStatus,
+    SongInfo,
+};
+use crate::track::format_duration;
+use std::{
+    time::{
+        Duration,
+    },
 };
 use serenity::{
+    builder::CreateEmbed,
     framework::standard::{
         CommandResult,
         macros::{
@@ -11,36 +24,128 @@ use serenity::{
     },
     client::Context,
     model::{
-        channel::Message,
+        channel::{
+            Message,
+            ReactionType,
+        },
     },
 };
 
+// songs on each page of the queue
+const PAGE_SIZE: usize = 10;
+// how long the arrows keep working after the last time someone used them
+const NAV_TIMEOUT: Duration = Duration::from_secs(120);
+const PREV_PAGE: &str = "◀️";
+const NEXT_PAGE: &str = "▶️";
+
+fn page_count(queue: &[SongInfo]) -> usize{
+    ((queue.len() + PAGE_SIZE - 1) / PAGE_SIZE).max(1)
+}
+
+// Fills in one page of the queue, page counts from 0
+fn queue_page(e: &mut CreateEmbed, status: &PlayerStatus, queue: &[SongInfo], page: usize){
+    e.title("Music Queue");
+
+    if let Some(song) = &status.song{
+        let length = song.track.length_text();
+        e.field("Now playing", format!("{} `{}/{}`", truncate(&song.track.title, 80), format_duration(status.position), length), false);
+    }
+
+    let start = page * PAGE_SIZE;
+    let lines = queue.iter().enumerate().skip(start).take(PAGE_SIZE).map(|(num, song)| {
+        let length = song.track.length_text();
+        format!("**{}.** {} `{}` <@{}>", num+1, truncate(&song.track.title, 80), length, song.track.requester.0)
+    }).collect::<Vec<String>>();
+    e.description(lines.join("\n"));
+
+    // everything left in the queue plus whatever's left of the song that's playing
+    let mut remaining = queue.iter().filter_map(|song| song.track.duration).sum::<Duration>();
+    if let Some(dur) = status.song.as_ref().and_then(|song| song.track.duration){
+        remaining += dur.checked_sub(status.position).unwrap_or_default();
+    }
+    // livestreams and files that don't say how long they are leave the total as a lower bound
+    let live = queue.iter().any(|song| song.track.duration.is_none());
+    e.footer(|f| f.text(format!(
+        "Page {}/{} | {} songs | {}{} remaining",
+        page+1,
+        page_count(queue),
+        queue.len(),
+        format_duration(remaining),
+        if live { "+" } else { "" },
+    )));
+}
 
 #[command]
 #[only_in(guilds)]
 async fn queue(ctx: &Context, msg:&Message) -> CommandResult {
     // Discord uses the name guild but it's the server
-    let guild = msg.guild(&ctx.cache).await.unwrap();
-    let guild_id = guild.id;
+    let guild_id = msg.guild_id.unwrap();
+    let player = get_player(ctx, guild_id).await;
 
-    let data = ctx.data.write().await;
-    let queue_map = data.get::<MusicQueue>().expect("Expected a song queue set up in the main.rs file").lock().await;
-    if let Some(queue) = queue_map.get(&guild_id){
-        check_msg(msg.channel_id.send_message(&ctx.http, |m| {
+    let (status, queue) = match (player.status().await, player.queue().await){
+        (Ok(status), Ok(queue)) => (status, queue),
+        (Err(err), _) | (_, Err(err)) => {
+            check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await);
+            return Ok(());
+        },
+    };
+    if queue.is_empty(){
+        check_msg(msg.channel_id.say(&ctx.http, "The queue is empty").await);
+        return Ok(());
+    }
+
+    let mut page = 0;
+    let mut queue_msg = match msg.channel_id.send_message(&ctx.http, |m| {
+        m.embed(|e| {
+            queue_page(e, &status, &queue, page);
+            e
+        });
+
+        m
+    }).await{
+        Ok(queue_msg) => queue_msg,
+        Err(err) => {
+            error!("Failed to send message: {:?}", err);
+            return Ok(());
+        },
+    };
+    // one page doesn't need any arrows
+    if page_count(&queue) == 1{
+        return Ok(());
+    }
+    for arrow in &[PREV_PAGE, NEXT_PAGE]{
+        if let Err(err) = queue_msg.react(&ctx.http, ReactionType::Unicode(arrow.to_string())).await{
+            error!("Failed to react to the queue: {:?}", err);
+        }
+    }
+
+    // adding or taking away a reaction both count as a press so nobody has to click twice
+    while let Some(action) = queue_msg.await_reaction(&ctx).author_id(msg.author.id).removed(true).timeout(NAV_TIMEOUT).await{
+        let pressed = match &action.as_inner_ref().emoji{
+            ReactionType::Unicode(emoji) => emoji.clone(),
+            _ => continue,
+        };
+        // the queue might have changed since the last page so look again
+        let (status, queue) = match (player.status().await, player.queue().await){
+            (Ok(status), Ok(queue)) => (status, queue),
+            _ => break,
+        };
+        let pages = page_count(&queue);
+        page = match pressed.as_str(){
+            PREV_PAGE => (page + pages - 1) % pages,
+            NEXT_PAGE => (page + 1) % pages,
+            _ => continue,
+        };
+        let edit = queue_msg.edit(&ctx, |m| {
             m.embed(|e| {
-                e.title("Music Queue");
-                for (num, song) in queue.iter().enumerate(){
-                    e.field(num+1, song.json_map.get("title").and_then(serde_json::Value::as_str).unwrap(), true);
-                }
+                queue_page(e, &status, &queue, page);
                 e
-            });
-
-            m
-        }).await);
-    }else{
-        check_msg(msg.channel_id.say(&ctx.http, "The queue is empty").await);
+            })
+        }).await;
+        if let Err(err) = edit{
+            error!("Failed to turn the queue page: {:?}", err);
+        }
     }
 
     Ok(())
 }
-
@@ -0,0 +1,76 @@
+use tracing::error;
+use crate::commands::{
+    check_msg,
+    play::play_tracks,
+};
+use crate::config::ConfigContainer;
+use crate::resolver::{
+    ResolverContainer,
+    TrackStream,
+};
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        Args,
+        macros::{
+            command,
+        },
+    },
+    client::Context,
+    model::{
+        channel::Message,
+    },
+};
+
+
+#[command]
+#[only_in(guilds)]
+async fn radio(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let config = ctx.data.read().await.get::<ConfigContainer>().expect("Expected the config set up in the main.rs file").clone();
+    if config.radio_stations.is_empty(){
+        check_msg(msg.channel_id.say(&ctx.http, "There aren't any radio stations set up, go yell at Brandon").await);
+        return Ok(());
+    }
+
+    let name = args.rest().trim();
+    let station = match config.radio_station(name){
+        Some(station) => station,
+        None => {
+            let names = config.radio_stations.iter().map(|station| station.name.as_str()).collect::<Vec<&str>>().join(", ");
+            if name.is_empty(){
+                check_msg(msg.channel_id.say(&ctx.http, &format!("Pick a station: {}", names)).await);
+            }else{
+                check_msg(msg.channel_id.say(&ctx.http, &format!("There's no station called {}, try one of {}", name, names)).await);
+            }
+            return Ok(());
+        },
+    };
+
+    let resolvers = ctx.data.read().await.get::<ResolverContainer>().expect("Expected the resolvers set up in the main.rs file").clone();
+    let mut tracks = match resolvers.resolve(&station.url, msg.author.id){
+        Ok(tracks) => tracks,
+        Err(err) => {
+            error!("Failed to resolve the {} station: {}", station.name, err);
+            check_msg(msg.channel_id.say(&ctx.http, "Couldn't tune in to that station, go yell at Brandon").await);
+            return Ok(());
+        },
+    };
+    let mut track = match tracks.next().await{
+        Some(Ok(track)) => track,
+        Some(Err(err)) => {
+            error!("Failed to resolve the {} station: {}", station.name, err);
+            check_msg(msg.channel_id.say(&ctx.http, "Couldn't tune in to that station, go yell at Brandon").await);
+            return Ok(());
+        },
+        None => {
+            check_msg(msg.channel_id.say(&ctx.http, "That station isn't on the air right now").await);
+            return Ok(());
+        },
+    };
+    // whatever the stream calls itself is usually less useful than the name we gave it
+    track.title = station.name.clone();
+    // it's a station, it goes on forever even if the link looks like a file
+    track.live = true;
+    track.duration = None;
+    play_tracks(ctx, msg, TrackStream::from_tracks(vec![track])).await
+}
@@ -0,0 +1,95 @@
+use tracing::error;
+use crate::commands::{
+    check_msg,
+    play::play_tracks,
+};
+use crate::resolver::{
+    ResolverContainer,
+    TrackStream,
+};
+use std::{
+    time::{
+        Duration,
+    },
+};
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        Args,
+        macros::{
+            command,
+        },
+    },
+    client::Context,
+    model::{
+        channel::Message,
+    },
+};
+
+// how many results get listed to pick from
+const SEARCH_RESULTS: usize = 5;
+// how long we wait for someone to pick one
+const PICK_TIMEOUT: Duration = Duration::from_secs(30);
+
+
+#[command]
+#[only_in(guilds)]
+async fn search(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let query = args.rest().trim().to_owned();
+    if query.is_empty(){
+        check_msg(msg.channel_id.say(&ctx.http, "You need something to search for after the command, doofus").await);
+        return Ok(());
+    }
+
+    let resolvers = ctx.data.read().await.get::<ResolverContainer>().expect("Expected the resolvers set up in the main.rs file").clone();
+    let mut results = match resolvers.search(&query, SEARCH_RESULTS, msg.author.id){
+        Ok(results) => results,
+        Err(err) => {
+            error!("Failed to search for {}: {}", query, err);
+            check_msg(msg.channel_id.say(&ctx.http, "The search didn't work, go yell at Brandon").await);
+            return Ok(());
+        },
+    };
+    let mut tracks = Vec::new();
+    while let Some(res) = results.next().await{
+        match res{
+            Ok(track) => tracks.push(track),
+            Err(err) => error!("Bad search result: {}", err),
+        }
+    }
+    if tracks.is_empty(){
+        check_msg(msg.channel_id.say(&ctx.http, "Couldn't find anything for that").await);
+        return Ok(());
+    }
+
+    check_msg(msg.channel_id.send_message(&ctx.http, |m| {
+        m.embed(|e| {
+            e.title(format!("Results for {}", query));
+            let lines = tracks.iter().enumerate().map(|(num, track)| {
+                let length = track.length_text();
+                format!("**{}.** {} ({})", num+1, track.title, length)
+            }).collect::<Vec<String>>();
+            e.description(lines.join("\n"));
+            e.footer(|f| f.text("Reply with the number you want, anything else cancels"));
+            e
+        });
+
+        m
+    }).await);
+
+    let reply = match msg.author.await_reply(&ctx).channel_id(msg.channel_id).timeout(PICK_TIMEOUT).await{
+        Some(reply) => reply,
+        None => {
+            check_msg(msg.reply(&ctx.http, "Took too long, never mind").await);
+            return Ok(());
+        },
+    };
+    let track = match reply.content.trim().parse::<usize>(){
+        Ok(num) if num >= 1 && num <= tracks.len() => tracks.swap_remove(num-1),
+        _ => {
+            check_msg(msg.channel_id.say(&ctx.http, "Search cancelled").await);
+            return Ok(());
+        },
+    };
+    play_tracks(ctx, msg, TrackStream::from_tracks(vec![track])).await
+}
@@ -0,0 +1,88 @@
+use crate::commands::{
+    check_msg,
+    get_player,
+};
+use crate::player::SeekTarget;
+use crate::track::{
+    format_duration,
+    parse_duration,
+};
+use std::{
+    time::{
+        Duration,
+    },
+};
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        Args,
+        macros::{
+            command,
+        },
+    },
+    client::Context,
+    model::{
+        channel::Message,
+    },
+};
+
+// how far ff and rewind go when they aren't told
+const DEFAULT_JUMP: Duration = Duration::from_secs(10);
+
+async fn _seek(ctx: &Context, msg: &Message, target: SeekTarget) -> CommandResult {
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    match get_player(ctx, guild_id).await.seek(target).await{
+        Ok(to) => check_msg(msg.channel_id.say(&ctx.http, &format!("Jumped to {}", format_duration(to))).await),
+        Err(err) => check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+async fn seek(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let to = match parse_duration(args.rest()){
+        Some(to) => to,
+        None => {
+            check_msg(msg.channel_id.say(&ctx.http, "You need a time like 1:23 after the command, doofus").await);
+            return Ok(());
+        },
+    };
+    _seek(ctx, msg, SeekTarget::To(to)).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases("forward")]
+async fn ff(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let by = match args.rest().trim(){
+        "" => DEFAULT_JUMP,
+        arg => match parse_duration(arg){
+            Some(by) => by,
+            None => {
+                check_msg(msg.channel_id.say(&ctx.http, "You need a number of seconds after the command, doofus").await);
+                return Ok(());
+            },
+        },
+    };
+    _seek(ctx, msg, SeekTarget::Forward(by)).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases("rw")]
+async fn rewind(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let by = match args.rest().trim(){
+        "" => DEFAULT_JUMP,
+        arg => match parse_duration(arg){
+            Some(by) => by,
+            None => {
+                check_msg(msg.channel_id.say(&ctx.http, "You need a number of seconds after the command, doofus").await);
+                return Ok(());
+            },
+        },
+    };
+    _seek(ctx, msg, SeekTarget::Back(by)).await
+}
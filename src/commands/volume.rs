@@ -0,0 +1,44 @@
+use crate::commands::{
+    check_msg,
+    get_player,
+};
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        Args,
+        macros::{
+            command,
+        },
+    },
+    client::Context,
+    model::{
+        channel::Message,
+    },
+};
+
+// !volume on its own says what it's at, !volume 50 sets it to half
+#[command]
+#[only_in(guilds)]
+#[aliases("vol")]
+async fn volume(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    // Discord uses the name guild but it's the server
+    let guild_id = msg.guild_id.unwrap();
+
+    let volume = match args.rest().trim().trim_end_matches('%'){
+        "" => None,
+        arg => match arg.parse::<u32>(){
+            Ok(volume) => Some(volume),
+            Err(_) => {
+                check_msg(msg.channel_id.say(&ctx.http, "You need a number like 50 after the command, doofus").await);
+                return Ok(());
+            },
+        },
+    };
+
+    match get_player(ctx, guild_id).await.set_volume(volume).await{
+        Ok(now) if volume.is_none() => check_msg(msg.channel_id.say(&ctx.http, &format!("The volume is at {}%", now)).await),
+        Ok(now) => check_msg(msg.channel_id.say(&ctx.http, &format!("Volume set to {}%", now)).await),
+        Err(err) => check_msg(msg.channel_id.say(&ctx.http, &err.to_string()).await),
+    }
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use std::{
+    env,
+    fmt,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use serenity::prelude::*;
+
+// Everything the bot reads out of the enviroment (or the .env file, dotenv loads that into the
+// enviroment before this runs). Nothing secret lives in the binary, leave a setting out and the bot
+// runs without it
+#[derive(Clone, Debug)]
+pub struct Config{
+    pub youtube_dl: YoutubeDlConfig,
+    // FFMPEG_BINARY and FFPROBE_BINARY, found on the PATH by default
+    pub ffmpeg: String,
+    pub ffprobe: String,
+    // LOCAL_MUSIC_DIR, the folder local files are allowed to play from
+    pub local_music_dir: Option<PathBuf>,
+    // SETTINGS_FILE, where each server's settings get saved, settings.json by default
+    pub settings_file: PathBuf,
+    // MAX_VOLUME, the loudest !volume goes in percent, 200 at most
+    pub max_volume: u32,
+    // RADIO_STATIONS, what !radio can play, written like name=url,othername=otherurl
+    pub radio_stations: Vec<RadioStation>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RadioStation{
+    pub name: String,
+    // anything the resolvers can play, usually a stream straight off the station's server
+    pub url: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct YoutubeDlConfig{
+    // YOUTUBE_DL_BINARY, youtube-dl by default but yt-dlp works just as well
+    pub binary: String,
+    // YOUTUBE_DL_USERNAME and YOUTUBE_DL_PASSWORD, only used when both are there
+    pub credentials: Option<Credentials>,
+    // YOUTUBE_DL_COOKIES, a cookies.txt file exported from a logged in browser
+    pub cookies: Option<PathBuf>,
+    // YOUTUBE_DL_NETRC, a .netrc file with the logins in it (needs yt-dlp for --netrc-location)
+    pub netrc: Option<PathBuf>,
+}
+
+#[derive(Clone)]
+pub struct Credentials{
+    pub username: String,
+    pub password: String,
+}
+
+// Keep the password out of the logs
+impl fmt::Debug for Credentials{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"<hidden>")
+            .finish()
+    }
+}
+
+// nobody gets to go louder than this no matter what MAX_VOLUME says
+pub const VOLUME_LIMIT: u32 = 200;
+
+// An empty variable counts as not being set, makes it easy to switch things off in the .env file
+fn var(name: &str) -> Option<String>{
+    env::var(name).ok().filter(|val| !val.trim().is_empty())
+}
+
+impl Config{
+    pub fn from_env() -> Config{
+        Config{
+            youtube_dl: YoutubeDlConfig::from_env(),
+            ffmpeg: var("FFMPEG_BINARY").unwrap_or_else(|| "ffmpeg".to_owned()),
+            ffprobe: var("FFPROBE_BINARY").unwrap_or_else(|| "ffprobe".to_owned()),
+            local_music_dir: var("LOCAL_MUSIC_DIR").map(PathBuf::from),
+            settings_file: var("SETTINGS_FILE").map_or_else(|| PathBuf::from("settings.json"), PathBuf::from),
+            max_volume: var("MAX_VOLUME")
+                .and_then(|max| max.trim().parse().ok())
+                .map_or(VOLUME_LIMIT, |max: u32| max.min(VOLUME_LIMIT)),
+            radio_stations: var("RADIO_STATIONS").map_or_else(Vec::new, |stations| parse_stations(&stations)),
+        }
+    }
+
+    // The station with that name, capitals don't matter
+    pub fn radio_station(&self, name: &str) -> Option<&RadioStation>{
+        self.radio_stations.iter().find(|station| station.name.eq_ignore_ascii_case(name))
+    }
+}
+
+// Anything without an = in it gets skipped, there's no good way to guess what it meant
+fn parse_stations(stations: &str) -> Vec<RadioStation>{
+    stations.split(',')
+        .filter_map(|station| {
+            let (name, url) = station.split_at(station.find('=')?);
+            let name = name.trim();
+            let url = url[1..].trim();
+            if name.is_empty() || url.is_empty(){
+                return None;
+            }
+            Some(RadioStation{
+                name: name.to_owned(),
+                url: url.to_owned(),
+            })
+        })
+        .collect()
+}
+
+pub struct ConfigContainer;
+
+impl TypeMapKey for ConfigContainer{
+    type Value = Arc<Config>;
+}
+
+impl YoutubeDlConfig{
+    pub fn from_env() -> YoutubeDlConfig{
+        let credentials = match (var("YOUTUBE_DL_USERNAME"), var("YOUTUBE_DL_PASSWORD")){
+            (Some(username), Some(password)) => Some(Credentials{
+                username: username,
+                password: password,
+            }),
+            _ => None,
+        };
+        YoutubeDlConfig{
+            binary: var("YOUTUBE_DL_BINARY").unwrap_or_else(|| "youtube-dl".to_owned()),
+            credentials: credentials,
+            cookies: var("YOUTUBE_DL_COOKIES").map(PathBuf::from),
+            netrc: var("YOUTUBE_DL_NETRC").map(PathBuf::from),
+        }
+    }
+
+    // The login related flags to hand youtube-dl, empty when there's nothing configured
+    pub fn auth_args(&self) -> Vec<String>{
+        let mut args = Vec::new();
+        if let Some(creds) = &self.credentials{
+            args.push("-u".to_owned());
+            args.push(creds.username.clone());
+            args.push("-p".to_owned());
+            args.push(creds.password.clone());
+        }
+        if let Some(cookies) = &self.cookies{
+            args.push("--cookies".to_owned());
+            args.push(cookies.to_string_lossy().into_owned());
+        }
+        if let Some(netrc) = &self.netrc{
+            args.push("--netrc".to_owned());
+            args.push("--netrc-location".to_owned());
+            args.push(netrc.to_string_lossy().into_owned());
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn stations(){
+        let stations = parse_stations(" lofi = https://example.com/lofi ,broken, =https://example.com,jazz=https://example.com/a=b");
+        let parsed = stations.iter().map(|s| (s.name.as_str(), s.url.as_str())).collect::<Vec<_>>();
+        assert_eq!(parsed, vec![
+            ("lofi", "https://example.com/lofi"),
+            ("jazz", "https://example.com/a=b"),
+        ]);
+        assert!(parse_stations("").is_empty());
+    }
+}
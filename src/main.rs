@@ -1,4 +1,11 @@
 mod commands;
+mod config;
+mod player;
+mod probe;
+mod process;
+mod resolver;
+mod settings;
+mod track;
 
 use std::{
     env,
@@ -7,12 +14,6 @@ use std::{
     },
     collections::{
         HashSet,
-        VecDeque,
-        HashMap,
-    },
-    time::{
-        Instant,
-        Duration,
     },
 };
 
@@ -33,8 +34,11 @@ use serenity::{
     model::{
         event::ResumedEvent, 
         gateway::Ready,
-        channel::Message,
-        id::GuildId,
+        channel::{
+            Message,
+            Reaction,
+            ReactionType,
+        },
     },
     prelude::*,
 };
@@ -50,6 +54,28 @@ use tracing_subscriber::{
     EnvFilter,
 };
 
+use config::{
+    Config,
+    ConfigContainer,
+};
+
+use settings::SettingsStore;
+
+use player::{
+    Players,
+    PlayerContainer,
+};
+
+use resolver::{
+    Resolvers,
+    ResolverContainer,
+    YoutubeDl,
+    HttpAudio,
+    LocalFile,
+    LocalLibrary,
+    LocalLibraryContainer,
+};
+
 use commands::{
     play::*,
     skip::*,
@@ -57,10 +83,17 @@ use commands::{
     pause::*,
     stop::*,
     queue::*,
-    SongInfo,
-    check_msg,
-    MusicQueue,
-    CurrentSong,
+    search::*,
+    edit::*,
+    modes::*,
+    nowplaying::*,
+    history::*,
+    seek::*,
+    volume::*,
+    audio::*,
+    eq::*,
+    radio::*,
+    local::*,
 };
 
 
@@ -96,15 +129,47 @@ impl EventHandler for Handler{
     async fn resume(&self, _: Context, _: ResumedEvent) {
         info!("Resumed");
     }
+
+    // the now playing controls, taking a reaction away counts as a press too so nobody has to
+    // click twice
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction){
+        now_playing_control(&ctx, &reaction).await;
+    }
+
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction){
+        now_playing_control(&ctx, &reaction).await;
+    }
+}
+
+async fn now_playing_control(ctx: &Context, reaction: &Reaction){
+    let guild_id = match reaction.guild_id{
+        Some(guild_id) => guild_id,
+        None => return,
+    };
+    // the bot puts the reactions there in the first place, those aren't presses
+    if reaction.user_id == Some(ctx.cache.current_user_id().await){
+        return;
+    }
+    let emoji = match &reaction.emoji{
+        ReactionType::Unicode(emoji) => emoji.clone(),
+        _ => return,
+    };
+    // reactions happen all the time, don't start up a player just to ignore one
+    let players = ctx.data.read().await.get::<PlayerContainer>().expect("Expected the players set up in the main.rs file").clone();
+    if let Some(player) = players.find(guild_id).await{
+        player.control(reaction.message_id, emoji);
+    }
 }
 
 #[group]
-#[commands(play, mechanicus, skip, skipto, add, pause, stop, queue)]
+#[commands(play, mechanicus, skip, skipto, add, playnext, pause, stop, queue, search, remove, move_song, swap, clear, shuffle, shufflemode, loop_mode, nowplaying, previous, replay, history, seek, ff, rewind, volume, normalize, filter, speed, eq, transition, radio, local)]
 struct General;
 
 #[tokio::main]
 async fn main() {
-    dotenv::dotenv().expect("failed to load .env file");
+    // the .env file is optional, everything in it can come from the real enviroment too. It gets
+    // loaded before the logger so RUST_LOG can live in there, whether it worked gets logged after
+    let dotenv = dotenv::dotenv();
 
     let subscriber = FmtSubscriber::builder()
         .with_env_filter(EnvFilter::from_default_env())
@@ -112,11 +177,22 @@ async fn main() {
 
     tracing::subscriber::set_global_default(subscriber).expect("Failed to start the logger");
 
+    if let Err(err) = dotenv{
+        info!("Not loading a .env file: {}", err);
+    }
+
+    let config = Arc::new(Config::from_env());
+    if config.youtube_dl.credentials.is_none(){
+        info!("No youtube-dl login configured, going without one");
+    }
+
+    // nothing stops the bot starting without them but it's better to hear about it now
+    process::check_binaries(&config).await;
+
     let token = env::var("DISCORD_TOKEN")
         .expect("Expected a token in the enviroment");
 
     let http = Http::new_with_token(&token);
-    let thread_http = Http::new_with_token(&token);
 
     // Use the discord api to find the bot's owners and ID
     let (owners, _bot_id) = match http.get_current_application_info().await {
@@ -147,14 +223,38 @@ async fn main() {
         .await
         .expect("Error creating client");
 
-    let music_queue = Arc::new(Mutex::new(HashMap::<GuildId, VecDeque<SongInfo>>::new()));
-    let current_song = Arc::new(Mutex::new(HashMap::<GuildId, (Option<Instant>, SongInfo)>::new()));
     let shard_manager = client.shard_manager.clone();
 
+    // checked in this order, youtube-dl takes anything the others don't want so it goes last
+    let mut resolvers = Resolvers::new();
+    // local files are only allowed when there's a library folder to keep them in
+    let mut library = None;
+    if let Some(dir) = &config.local_music_dir{
+        match LocalLibrary::open(dir.clone(), config.ffprobe.clone()){
+            Ok(opened) => {
+                resolvers = resolvers.with(LocalFile::new(opened.clone()));
+                library = Some(opened);
+            },
+            Err(err) => error!("Can't use {:?} as the local music library: {:?}", dir, err),
+        }
+    }
+    let resolvers = Arc::new(resolvers
+        .with(HttpAudio::new(config.ffprobe.clone()))
+        .with(YoutubeDl::new(config.youtube_dl.clone())));
+
+    // each guild gets its own player when it first needs one, the players need the resolvers to
+    // look songs up again before they play
+    let store = Arc::new(SettingsStore::load(config.settings_file.clone()));
+    let players = Arc::new(Players::new(client.cache_and_http.http.clone(), config.clone(), store, resolvers.clone()));
+
     let mut data = client.data.write().await; // Data to be shared across all the commands
     data.insert::<ShardManagerContainer>(client.shard_manager.clone());
-    data.insert::<MusicQueue>(music_queue.clone()); 
-    data.insert::<CurrentSong>(current_song.clone());
+    data.insert::<PlayerContainer>(players);
+    data.insert::<ResolverContainer>(resolvers);
+    data.insert::<ConfigContainer>(config);
+    if let Some(library) = library{
+        data.insert::<LocalLibraryContainer>(library);
+    }
     drop(data);
 
 
@@ -163,50 +263,6 @@ async fn main() {
         shard_manager.lock().await.shutdown_all().await;
     });
 
-    // The thread that monitors the music queue and plays the next song where applicable
-    tokio::spawn(async move {
-        loop{
-            for (serv, (pos_ins, song)) in current_song.lock().await.iter_mut(){
-                // if there's None here that means the queue is paused so don't do anything
-                if let Some(ins) = pos_ins{
-                    // ins is the moment the song started playing
-                    let song_dur = Duration::from_secs(song.json_map.get("duration").and_then(serde_json::Value::as_u64).unwrap());
-                    if ins.elapsed() >= song_dur.checked_add(Duration::from_secs(10)).unwrap(){ //adds a buffer in between songs, less jarring this way
-                        let mut mq = music_queue.lock().await;
-                        let q = mq.entry(serv.clone()).or_insert(VecDeque::new());
-                        // get the next song if it's there, if not than we ran through the queue
-                        // and we do nothing 
-                        if let Some(next_song) = q.pop_front(){
-                            match sb.get(serv.clone()){// Songbird instance from the main thread
-                                Some(handler_lock) => {
-                                    let mut handler = handler_lock.lock().await;
-
-                                    let source = match commands::make_source(&next_song){
-                                        Some(src) => src,
-                                        None => {
-                                            error!("Failed to play the next song");
-                                            check_msg(next_song.channel.say(&thread_http, "Can't play the next queued song").await);
-                                            continue;
-                                        }
-                                    };
-
-                                    handler.play_only_source(source);
-                                    check_msg(next_song.channel.say(&thread_http, &format!("Playing {}", next_song.json_map.get("title").and_then(serde_json::Value::as_str).unwrap())).await);
-                                    *pos_ins = Some(Instant::now());
-                                    *song = next_song;
-                                },
-                                // we've been disconnected somehow, put the stream on pause
-                                None => {
-                                    *pos_ins = None
-                                },
-                            };
-                        }
-                    }
-                }
-            }
-        }
-    });
-
     if let Err(err) = client.start().await {
         error!("Client error: {:?}", err);
     }
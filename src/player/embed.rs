@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use serenity::builder::CreateEmbed;
+
+use crate::{
+    commands::truncate,
+    player::PlayerStatus,
+    track::format_duration,
+};
+
+// The reactions on the now playing message and what they do
+pub const PLAY_PAUSE: &str = "⏯️";
+pub const SKIP: &str = "⏭️";
+pub const STOP: &str = "⏹️";
+pub const SHUFFLE: &str = "🔀";
+pub const CONTROLS: &[&str] = &[PLAY_PAUSE, SKIP, STOP, SHUFFLE];
+
+const BAR_LENGTH: usize = 20;
+
+// ▬▬▬▬▬▬🔘▬▬▬▬▬▬▬▬▬▬▬▬▬▬, the dot is how far into the song we are
+pub fn progress_bar(position: Duration, length: Duration) -> String{
+    let done = if length.as_secs_f64() > 0.0{
+        ((position.as_secs_f64() / length.as_secs_f64()) * BAR_LENGTH as f64) as usize
+    }else{
+        0
+    };
+    let done = done.min(BAR_LENGTH - 1);
+    let mut bar = "▬".repeat(done);
+    bar.push('🔘');
+    bar.push_str(&"▬".repeat(BAR_LENGTH - 1 - done));
+    bar
+}
+
+pub fn now_playing_embed(e: &mut CreateEmbed, status: &PlayerStatus){
+    let song = match &status.song{
+        Some(song) => song,
+        None => {
+            e.title("Nothing is playing");
+            return;
+        },
+    };
+    let track = &song.track;
+
+    e.title(truncate(&track.title, 250));
+    if let Some(url) = &track.webpage_url{
+        e.url(url);
+    }
+    if let Some(thumbnail) = &track.thumbnail{
+        e.thumbnail(thumbnail);
+    }
+    if let Some(uploader) = &track.uploader{
+        e.author(|a| a.name(truncate(uploader, 250)));
+    }
+
+    let state = if status.paused { "⏸️" } else { "▶️" };
+    let progress = match track.duration{
+        _ if track.live => format!("{} 🔴 LIVE `{}`", state, format_duration(status.position)),
+        Some(length) => format!("{} {} `{}/{}`", state, progress_bar(status.position, length), format_duration(status.position), format_duration(length)),
+        // no idea how long it is, so no bar either
+        None => format!("{} `{}`", state, format_duration(status.position)),
+    };
+    e.description(progress);
+
+    e.field("Requested by", format!("<@{}>", track.requester.0), true);
+    // which site or resolver it came from, youtube, soundcloud, local, ...
+    if let Some(extractor) = &track.extractor{
+        e.field("Source", extractor, true);
+    }
+    e.field("Loop", status.loop_mode, true);
+    e.field("Shuffle", if status.shuffle { "on" } else { "off" }, true);
+    e.field("Volume", format!("{}%", status.volume), true);
+    if let Some(filter) = status.filter{
+        e.field("Filter", filter, true);
+    }
+    if status.speed != 1.0{
+        e.field("Speed", format!("{}x", status.speed), true);
+    }
+    let up_next = match (&status.up_next, status.queue_len){
+        (_, 0) => "Nothing, the queue is empty".to_owned(),
+        (Some(next), _) => truncate(&next.track.title, 250),
+        // shuffle picks the next one when it gets there
+        (None, _) => format!("Something random out of {} songs", status.queue_len),
+    };
+    e.field("Up next", up_next, false);
+    e.footer(|f| f.text(format!("{} pause/resume | {} skip | {} stop | {} shuffle", PLAY_PAUSE, SKIP, STOP, SHUFFLE)));
+}
@@ -0,0 +1,129 @@
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::settings::GuildSettings;
+
+// EBU R128 loudness, -16 LUFS is about where the streaming sites sit. This is the one pass version,
+// it adjusts as it goes instead of measuring the whole song first
+const LOUDNORM: &str = "loudnorm=I=-16:TP=-1.5:LRA=11";
+
+// atempo gets weird outside of this
+pub const MIN_SPEED: f64 = 0.5;
+pub const MAX_SPEED: f64 = 2.0;
+
+// The middle of each equalizer band in Hz, they're an octave apart
+pub const EQ_BANDS: [u32; 10] = [31, 62, 125, 250, 500, 1000, 2000, 4000, 8000, 16000];
+// dB either way, any more than this just clips
+pub const MAX_GAIN: f64 = 12.0;
+
+pub type EqGains = [f64; 10];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterPreset{
+    BassBoost,
+    // faster and higher pitched
+    Nightcore,
+    // slower and lower pitched
+    Vaporwave,
+    Tremolo,
+    // the sound goes round and round your head, best with headphones
+    Rotate,
+}
+
+pub const PRESETS: &[FilterPreset] = &[
+    FilterPreset::BassBoost,
+    FilterPreset::Nightcore,
+    FilterPreset::Vaporwave,
+    FilterPreset::Tremolo,
+    FilterPreset::Rotate,
+];
+
+impl FilterPreset{
+    fn filter(self) -> &'static str{
+        match self{
+            FilterPreset::BassBoost => "bass=g=10:f=110:w=0.6",
+            // changing the sample rate without resampling changes the speed and the pitch together,
+            // the aresample first makes sure it's starting from 48k whatever the song was
+            FilterPreset::Nightcore => "aresample=48000,asetrate=48000*1.25,aresample=48000",
+            FilterPreset::Vaporwave => "aresample=48000,asetrate=48000*0.8,aresample=48000",
+            FilterPreset::Tremolo => "tremolo=f=6:d=0.6",
+            FilterPreset::Rotate => "apulsator=hz=0.125",
+        }
+    }
+
+    // how much faster than normal the song goes through with this on
+    fn rate(self) -> f64{
+        match self{
+            FilterPreset::Nightcore => 1.25,
+            FilterPreset::Vaporwave => 0.8,
+            _ => 1.0,
+        }
+    }
+}
+
+impl FromStr for FilterPreset{
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<FilterPreset, ()>{
+        match s.to_lowercase().as_str(){
+            "bassboost" | "bass" => Ok(FilterPreset::BassBoost),
+            "nightcore" => Ok(FilterPreset::Nightcore),
+            "vaporwave" => Ok(FilterPreset::Vaporwave),
+            "tremolo" => Ok(FilterPreset::Tremolo),
+            "8d" | "rotate" => Ok(FilterPreset::Rotate),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for FilterPreset{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            FilterPreset::BassBoost => write!(f, "bassboost"),
+            FilterPreset::Nightcore => write!(f, "nightcore"),
+            FilterPreset::Vaporwave => write!(f, "vaporwave"),
+            FilterPreset::Tremolo => write!(f, "tremolo"),
+            FilterPreset::Rotate => write!(f, "8d"),
+        }
+    }
+}
+
+// The -af chain ffmpeg runs a guild's audio through, None when there's nothing to do
+pub fn filter_chain(settings: &GuildSettings) -> Option<String>{
+    let mut filters = Vec::new();
+    if let Some(preset) = settings.filter{
+        filters.push(preset.filter().to_owned());
+    }
+    if settings.speed != 1.0{
+        filters.push(format!("atempo={}", settings.speed));
+    }
+    // one peaking filter per band that's actually been moved, an octave wide so they meet in the middle
+    for (freq, gain) in EQ_BANDS.iter().zip(settings.eq.iter()){
+        if *gain != 0.0{
+            filters.push(format!("equalizer=f={}:t=o:w=1:g={}", freq, gain));
+        }
+    }
+    // loudnorm stays last so whatever comes before it still ends up at the same loudness
+    if settings.normalize{
+        filters.push(LOUDNORM.to_owned());
+    }
+    if filters.is_empty(){
+        None
+    }else{
+        Some(filters.join(","))
+    }
+}
+
+// How many seconds of the song go by every second, the progress bar and seeking need this to keep
+// up when something speeds it up
+pub fn playback_rate(settings: &GuildSettings) -> f64{
+    settings.speed * settings.filter.map_or(1.0, FilterPreset::rate)
+}
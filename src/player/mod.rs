@@ -0,0 +1,1806 @@
+use std::{
+    fmt,
+    error::Error,
+    str::FromStr,
+    time::{
+        Instant,
+        Duration,
+    },
+    collections::{
+        VecDeque,
+        HashMap,
+    },
+    sync::{
+        Arc,
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+    },
+};
+
+use serenity::{
+    async_trait,
+    http::Http,
+    model::{
+        channel::ReactionType,
+        id::{
+            ChannelId,
+            GuildId,
+            MessageId,
+        },
+    },
+    prelude::*,
+};
+
+use songbird::{
+    Call,
+    Event,
+    EventContext,
+    EventHandler as VoiceEventHandler,
+    TrackEvent,
+    create_player,
+    tracks::{
+        Track as SongbirdTrack,
+        TrackError,
+        TrackHandle,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use rand::{
+    Rng,
+    seq::SliceRandom,
+};
+
+use tokio::{
+    sync::{
+        mpsc,
+        oneshot,
+    },
+    time,
+};
+
+use tracing::{error, info};
+
+use crate::{
+    commands::check_msg,
+    config::Config,
+    process::StderrLog,
+    resolver::{
+        ResolveError,
+        Resolvers,
+    },
+    settings::{
+        GuildSettings,
+        SettingsStore,
+    },
+    track::Track,
+};
+
+pub mod embed;
+pub mod filters;
+pub mod source;
+
+pub use source::make_source;
+pub use filters::{
+    EqGains,
+    FilterPreset,
+};
+
+// how many songs the history remembers
+const HISTORY_LEN: usize = 25;
+// how often the now playing message gets its progress bar moved along
+const NOW_PLAYING_REFRESH: Duration = Duration::from_secs(15);
+// how often a crossfade moves the volumes along, this is how smooth the fade is
+const TRANSITION_TICK: Duration = Duration::from_millis(100);
+// the longest gap or crossfade you can ask for in seconds
+pub const MAX_TRANSITION: u64 = 12;
+// how long before a song ends the next one gets looked up again and started up
+const PRELOAD_AHEAD: Duration = Duration::from_secs(20);
+// a song that stops more than this short of its end didn't finish, it fell over
+const EARLY_SLACK: Duration = Duration::from_secs(10);
+
+// where song ids come from, every song that gets queued takes the next one
+static NEXT_SONG_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone)]
+pub struct SongInfo{
+    pub track: Track,
+    // where to send messages about the song
+    pub channel: ChannelId,
+    // tells this request apart from every other one, even the same song queued twice. It stays the
+    // same when the song gets looked up again
+    pub id: u64,
+}
+
+impl SongInfo{
+    pub fn new(track: Track, channel: ChannelId) -> SongInfo{
+        SongInfo{
+            track: track,
+            channel: channel,
+            id: NEXT_SONG_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+pub struct NowPlaying{
+    pub song: SongInfo,
+    // the handle songbird gave back when the song started, this is how we tell which song an
+    // event belongs to and how we pause and resume it
+    pub handle: TrackHandle,
+    // how long the song played for before the last pause
+    pub played: Duration,
+    // the moment the song last started or resumed playing, None means the song is paused
+    pub resumed: Option<Instant>,
+    // how fast the filters make the song go, 1.0 is normal
+    pub rate: f64,
+    // whether this is already the second go after the stream died, it doesn't get a third
+    pub retried: bool,
+    // what ffmpeg had to say about it, for when it falls over
+    pub stderr: StderrLog,
+}
+
+impl NowPlaying{
+    // offset is where in the song the track started, for when it got seeked
+    pub fn new(song: SongInfo, handle: TrackHandle, stderr: StderrLog, offset: Duration, rate: f64) -> NowPlaying{
+        NowPlaying{
+            song: song,
+            handle: handle,
+            played: offset,
+            resumed: Some(Instant::now()),
+            rate: rate,
+            retried: false,
+            stderr: stderr,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool{
+        self.resumed.is_none()
+    }
+
+    // How far into the song we are
+    pub fn position(&self) -> Duration{
+        match self.resumed{
+            Some(ins) => self.played + ins.elapsed().mul_f64(self.rate),
+            None => self.played,
+        }
+    }
+
+    pub fn pause(&mut self){
+        self.played = self.position();
+        self.resumed = None;
+    }
+
+    pub fn resume(&mut self){
+        if self.resumed.is_none(){
+            self.resumed = Some(Instant::now());
+        }
+    }
+
+    // How long until it's over in real time, the speed filters change that. None for a livestream
+    pub fn left(&self) -> Option<Duration>{
+        let length = self.song.track.duration?;
+        Some(length.checked_sub(self.position()).unwrap_or_default().div_f64(self.rate))
+    }
+
+    // ffmpeg gives up straight away on a link that's expired (youtube's last a few hours) and
+    // partway through when the connection drops, either way the song ends well before it should.
+    // errored is whether songbird said the track fell over instead of just ending
+    pub fn died_early(&self, errored: bool) -> bool{
+        if errored{
+            return true;
+        }
+        // livestreams end whenever they want, just not right away
+        if self.song.track.live{
+            return self.position() < EARLY_SLACK;
+        }
+        match self.song.track.duration{
+            Some(length) => self.position() + EARLY_SLACK < length,
+            // no idea when it should have ended, only one that barely got going counts. ffmpeg
+            // complaining isn't enough, it moans about plenty of things it gets past just fine
+            None => self.position() < EARLY_SLACK,
+        }
+    }
+}
+
+// A snapshot of what a guild's player is up to
+#[derive(Clone)]
+pub struct PlayerStatus{
+    pub song: Option<SongInfo>,
+    // how far into the song it is
+    pub position: Duration,
+    pub paused: bool,
+    pub loop_mode: LoopMode,
+    pub shuffle: bool,
+    // what comes after this song, None when the queue is empty or shuffle makes it anyone's guess
+    pub up_next: Option<SongInfo>,
+    pub queue_len: usize,
+    pub volume: u32,
+    pub filter: Option<FilterPreset>,
+    pub speed: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum SeekTarget{
+    To(Duration),
+    Forward(Duration),
+    Back(Duration),
+}
+
+// The ways !eq can change the equalizer, bands are counted from 1
+#[derive(Clone, Debug)]
+pub enum EqChange{
+    Show,
+    Band(usize, f64),
+    All(EqGains),
+    Reset,
+    Save(String),
+    Load(String),
+    Delete(String),
+}
+
+// What the equalizer is set to and the profiles the guild has saved
+#[derive(Clone, Debug)]
+pub struct EqState{
+    pub gains: EqGains,
+    pub profiles: Vec<String>,
+}
+
+// What happens between songs, the numbers are seconds
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transition{
+    // the next song starts the moment the last one ends
+    Gapless,
+    // a bit of quiet between songs
+    Gap(u64),
+    // the next song fades in over the end of the last one
+    Crossfade(u64),
+}
+
+impl fmt::Display for Transition{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            Transition::Gapless => write!(f, "no gap"),
+            Transition::Gap(secs) => write!(f, "a {} second gap", secs),
+            Transition::Crossfade(secs) => write!(f, "a {} second crossfade", secs),
+        }
+    }
+}
+
+// The old song fading out under the new one
+struct Fade{
+    outgoing: TrackHandle,
+    started: Instant,
+    length: Duration,
+}
+
+// The next song, looked up again and with ffmpeg already going so it starts the moment it's needed
+enum Preload{
+    // the resolver's still working on it. It also stays like this if starting ffmpeg early didn't
+    // work, it gets another go when the song actually comes up
+    Pending(SongInfo),
+    Ready(Warm),
+}
+
+impl Preload{
+    fn song(&self) -> &SongInfo{
+        match self{
+            Preload::Pending(song) => song,
+            Preload::Ready(warm) => &warm.song,
+        }
+    }
+}
+
+struct Warm{
+    song: SongInfo,
+    track: SongbirdTrack,
+    handle: TrackHandle,
+    stderr: StderrLog,
+    // the filters ffmpeg got started with, it's no good once they change
+    filters: Option<String>,
+}
+
+// Why a song got looked up again
+enum Lookup{
+    Preload,
+    // the stream died partway through, this is where
+    Retry(Duration),
+}
+
+// What a lookup sends back, why it happened, the song it started from and how it went
+type Refreshed = (Lookup, SongInfo, Result<Track, ResolveError>);
+
+// A now playing message that finished sending, which post it was and where it ended up
+type Posted = (u64, ChannelId, MessageId);
+
+fn same_song(a: &SongInfo, b: &SongInfo) -> bool{
+    a.id == b.id
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoopMode{
+    Off,
+    // play the same song over and over
+    Track,
+    // songs go back on the end of the queue once they're done
+    Queue,
+}
+
+impl LoopMode{
+    // off goes to track goes to queue goes back to off
+    pub fn next(self) -> LoopMode{
+        match self{
+            LoopMode::Off => LoopMode::Track,
+            LoopMode::Track => LoopMode::Queue,
+            LoopMode::Queue => LoopMode::Off,
+        }
+    }
+}
+
+impl FromStr for LoopMode{
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<LoopMode, ()>{
+        match s.to_lowercase().as_str(){
+            "off" | "none" => Ok(LoopMode::Off),
+            "track" | "song" | "one" => Ok(LoopMode::Track),
+            "queue" | "all" => Ok(LoopMode::Queue),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for LoopMode{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            LoopMode::Off => write!(f, "off"),
+            LoopMode::Track => write!(f, "track"),
+            LoopMode::Queue => write!(f, "queue"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PlayerError{
+    NotConnected,
+    NothingPlaying,
+    AlreadyPaused,
+    AlreadyPlaying,
+    EmptyQueue,
+    OutOfRange,
+    NoHistory,
+    CantSeek,
+    PastTheEnd,
+    // holds the loudest it's allowed to go
+    TooLoud(u32),
+    TooLong,
+    BadSpeed,
+    BadBand,
+    BadGain,
+    NoProfile(String),
+    // the song's title and why it wouldn't play
+    Source(String, String),
+    Track(TrackError),
+    Gone,
+}
+
+// These get said straight to whoever ran the command
+impl fmt::Display for PlayerError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            PlayerError::NotConnected => write!(f, "I'm not in a voice channel"),
+            PlayerError::NothingPlaying => write!(f, "Nothing is playing"),
+            PlayerError::AlreadyPaused => write!(f, "It's already paused"),
+            PlayerError::AlreadyPlaying => write!(f, "It's already playing"),
+            PlayerError::EmptyQueue => write!(f, "There's nothing in the queue"),
+            PlayerError::OutOfRange => write!(f, "There's not enough songs in the queue"),
+            PlayerError::NoHistory => write!(f, "There's nothing to go back to"),
+            PlayerError::CantSeek => write!(f, "You can't seek in a livestream"),
+            PlayerError::PastTheEnd => write!(f, "That's past the end of the song"),
+            PlayerError::BadSpeed => write!(f, "The speed has to be between {} and {}", filters::MIN_SPEED, filters::MAX_SPEED),
+            PlayerError::BadBand => write!(f, "The bands go from 1 to {}", filters::EQ_BANDS.len()),
+            PlayerError::BadGain => write!(f, "Bands only go {} dB up or down", filters::MAX_GAIN),
+            PlayerError::NoProfile(name) => write!(f, "There's no equalizer profile called {}", name),
+            PlayerError::TooLong => write!(f, "Gaps and crossfades only go up to {} seconds", MAX_TRANSITION),
+            PlayerError::TooLoud(max) => write!(f, "The volume only goes up to {}, think of everyone's ears", max),
+            PlayerError::Source(title, why) => write!(f, "Couldn't play {}: {}", title, why),
+            PlayerError::Track(_) => write!(f, "Something went wrong with the song, go yell at Brandon"),
+            PlayerError::Gone => write!(f, "The player for this server fell over, go yell at Brandon"),
+        }
+    }
+}
+
+impl Error for PlayerError{}
+
+impl From<TrackError> for PlayerError{
+    fn from(err: TrackError) -> PlayerError{
+        PlayerError::Track(err)
+    }
+}
+
+pub type PlayerResult<T> = Result<T, PlayerError>;
+
+// Everything a guild's player can be asked to do, the ones with a sender send their answer back on it
+pub enum PlayerCommand{
+    Attach(Arc<Mutex<Call>>),
+    Play(SongInfo, oneshot::Sender<PlayerResult<()>>),
+    Enqueue(SongInfo, oneshot::Sender<usize>),
+    Skip(oneshot::Sender<PlayerResult<Option<SongInfo>>>),
+    SkipTo(usize, oneshot::Sender<PlayerResult<SongInfo>>),
+    Pause(oneshot::Sender<PlayerResult<SongInfo>>),
+    Resume(oneshot::Sender<PlayerResult<SongInfo>>),
+    Stop(oneshot::Sender<PlayerResult<()>>),
+    Queue(oneshot::Sender<Vec<SongInfo>>),
+    Status(oneshot::Sender<PlayerStatus>),
+    // queue positions from here on are counted from 1 like the queue command shows them
+    Insert(usize, SongInfo, oneshot::Sender<usize>),
+    Remove(usize, usize, oneshot::Sender<PlayerResult<Vec<SongInfo>>>),
+    Move(usize, usize, oneshot::Sender<PlayerResult<SongInfo>>),
+    Swap(usize, usize, oneshot::Sender<PlayerResult<(SongInfo, SongInfo)>>),
+    Clear(oneshot::Sender<usize>),
+    Shuffle(oneshot::Sender<usize>),
+    // None flips it
+    SetShuffle(Option<bool>, oneshot::Sender<bool>),
+    // None moves on to the next mode
+    SetLoop(Option<LoopMode>, oneshot::Sender<LoopMode>),
+    // posts a fresh now playing message in the channel, the old one goes away
+    ShowNowPlaying(ChannelId, oneshot::Sender<PlayerResult<()>>),
+    // someone pressed one of the reactions on a message, it's only ours if it's the now playing one
+    Control(MessageId, String),
+    Previous(oneshot::Sender<PlayerResult<SongInfo>>),
+    Replay(oneshot::Sender<PlayerResult<SongInfo>>),
+    History(oneshot::Sender<Vec<SongInfo>>),
+    Seek(SeekTarget, oneshot::Sender<PlayerResult<Duration>>),
+    // None just asks what it is
+    SetVolume(Option<u32>, oneshot::Sender<PlayerResult<u32>>),
+    // None flips it
+    SetNormalize(Option<bool>, oneshot::Sender<PlayerResult<bool>>),
+    // None takes the filter off
+    SetFilter(Option<FilterPreset>, oneshot::Sender<PlayerResult<()>>),
+    SetSpeed(f64, oneshot::Sender<PlayerResult<()>>),
+    Eq(EqChange, oneshot::Sender<PlayerResult<EqState>>),
+    // None just asks what it is
+    SetTransition(Option<Transition>, oneshot::Sender<PlayerResult<Transition>>),
+    // errored is whether it fell over instead of finishing
+    TrackEnded(TrackHandle, bool),
+}
+
+// One of these runs for every guild, it's the only thing that touches that guild's queue, song and
+// voice connection so nothing ever has to wait on another guild
+struct GuildPlayer{
+    guild_id: GuildId,
+    http: Arc<Http>,
+    config: Arc<Config>,
+    store: Arc<SettingsStore>,
+    resolvers: Arc<Resolvers>,
+    // this guild's part of the store, loaded when the player starts up
+    settings: GuildSettings,
+    call: Option<Arc<Mutex<Call>>>,
+    queue: VecDeque<SongInfo>,
+    current: Option<NowPlaying>,
+    // the songs that played most recently, newest first
+    history: VecDeque<SongInfo>,
+    // pick the next song at random instead of off the front
+    shuffle: bool,
+    loop_mode: LoopMode,
+    // the message with the progress bar and the controls on it
+    now_playing_msg: Option<(ChannelId, MessageId)>,
+    // counts up every time a now playing message gets posted or cleared, a message that shows up
+    // after it's already been replaced gets deleted
+    now_playing_post: u64,
+    // sending the now playing message happens off on its own, it reports back here when it's up
+    posted_tx: mpsc::UnboundedSender<Posted>,
+    // the song before this one on its way out during a crossfade
+    fade: Option<Fade>,
+    // when the next song starts if we're sitting in a gap between songs
+    gap_until: Option<Instant>,
+    preload: Option<Preload>,
+    // the song whose stream died, waiting on a new link before it gets another go
+    retrying: Option<SongInfo>,
+    // lookups run off on their own and send what they found back here
+    refreshed_tx: mpsc::UnboundedSender<Refreshed>,
+}
+
+impl GuildPlayer{
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<PlayerCommand>, mut refreshed_rx: mpsc::UnboundedReceiver<Refreshed>, mut posted_rx: mpsc::UnboundedReceiver<Posted>){
+        self.settings = self.store.get(self.guild_id).await;
+        // the maximum might have come down since the guild saved its volume
+        self.settings.volume = self.settings.volume.min(self.config.max_volume);
+        let mut refresh = time::interval(NOW_PLAYING_REFRESH);
+        loop{
+            self.drop_stale_preload();
+            // nothing wakes up for transitions unless something's actually coming up
+            let deadline = self.next_deadline();
+            let wake = time::Instant::from_std(deadline.unwrap_or_else(Instant::now));
+            tokio::select!{
+                cmd = rx.recv() => match cmd{
+                    Some(cmd) => self.handle(cmd).await,
+                    None => break,
+                },
+                _ = refresh.tick() => {
+                    // nothing moves while it's paused
+                    if self.current.as_ref().map_or(false, |now_playing| !now_playing.is_paused()){
+                        self.refresh_now_playing();
+                    }
+                },
+                _ = time::sleep_until(wake), if deadline.is_some() => self.transition_tick().await,
+                Some((lookup, song, fresh)) = refreshed_rx.recv() => match lookup{
+                    Lookup::Preload => self.warm_up(song, fresh),
+                    Lookup::Retry(position) => self.retry(song, position, fresh).await,
+                },
+                Some((post, channel, message_id)) = posted_rx.recv() => self.posted(post, channel, message_id),
+            }
+        }
+        info!("Player for guild {} shut down", self.guild_id);
+    }
+
+    async fn handle(&mut self, cmd: PlayerCommand){
+        // the other end might have given up waiting, nothing to do about that so the sends are ignored
+        match cmd{
+            PlayerCommand::Attach(call) => self.call = Some(call),
+            PlayerCommand::Play(song, tx) => {
+                let _ = tx.send(self.play(song).await);
+            },
+            PlayerCommand::Enqueue(song, tx) => {
+                self.queue.push_back(song);
+                let _ = tx.send(self.queue.len());
+            },
+            PlayerCommand::Skip(tx) => {
+                let _ = tx.send(self.skip().await);
+            },
+            PlayerCommand::SkipTo(num, tx) => {
+                let _ = tx.send(self.skip_to(num).await);
+            },
+            PlayerCommand::Pause(tx) => {
+                let _ = tx.send(self.pause());
+                self.refresh_now_playing();
+            },
+            PlayerCommand::Resume(tx) => {
+                let _ = tx.send(self.resume().await);
+                self.refresh_now_playing();
+            },
+            PlayerCommand::Stop(tx) => {
+                let _ = tx.send(self.stop().await);
+            },
+            PlayerCommand::Queue(tx) => {
+                let _ = tx.send(self.queue.iter().cloned().collect());
+            },
+            PlayerCommand::Status(tx) => {
+                let _ = tx.send(self.status());
+            },
+            PlayerCommand::Insert(pos, song, tx) => {
+                // anything past the end just goes on the end
+                let index = pos.saturating_sub(1).min(self.queue.len());
+                self.queue.insert(index, song);
+                let _ = tx.send(self.queue.len());
+            },
+            PlayerCommand::Remove(first, last, tx) => {
+                let _ = tx.send(self.remove(first, last));
+            },
+            PlayerCommand::Move(from, to, tx) => {
+                let _ = tx.send(self.move_song(from, to));
+            },
+            PlayerCommand::Swap(first, second, tx) => {
+                let _ = tx.send(self.swap(first, second));
+            },
+            PlayerCommand::Clear(tx) => {
+                let cleared = self.queue.len();
+                self.queue.clear();
+                let _ = tx.send(cleared);
+            },
+            PlayerCommand::Shuffle(tx) => {
+                self.queue.make_contiguous().shuffle(&mut rand::thread_rng());
+                let _ = tx.send(self.queue.len());
+            },
+            PlayerCommand::SetShuffle(shuffle, tx) => {
+                self.shuffle = shuffle.unwrap_or(!self.shuffle);
+                let _ = tx.send(self.shuffle);
+                self.refresh_now_playing();
+            },
+            PlayerCommand::SetLoop(mode, tx) => {
+                self.loop_mode = mode.unwrap_or_else(|| self.loop_mode.next());
+                let _ = tx.send(self.loop_mode);
+                self.refresh_now_playing();
+            },
+            PlayerCommand::ShowNowPlaying(channel, tx) => {
+                if self.current.is_none(){
+                    let _ = tx.send(Err(PlayerError::NothingPlaying));
+                }else{
+                    self.post_now_playing(channel);
+                    let _ = tx.send(Ok(()));
+                }
+            },
+            PlayerCommand::Control(message_id, emoji) => self.control(message_id, &emoji).await,
+            PlayerCommand::Previous(tx) => {
+                let _ = tx.send(self.previous().await);
+            },
+            PlayerCommand::Replay(tx) => {
+                let _ = tx.send(self.replay().await);
+            },
+            PlayerCommand::History(tx) => {
+                let _ = tx.send(self.history.iter().cloned().collect());
+            },
+            PlayerCommand::Seek(target, tx) => {
+                let _ = tx.send(self.seek(target).await);
+            },
+            PlayerCommand::SetVolume(volume, tx) => {
+                let _ = tx.send(self.set_volume(volume).await);
+                self.refresh_now_playing();
+            },
+            PlayerCommand::SetNormalize(normalize, tx) => {
+                let _ = tx.send(self.set_normalize(normalize).await);
+            },
+            PlayerCommand::SetFilter(filter, tx) => {
+                let _ = tx.send(self.set_filter(filter).await);
+                self.refresh_now_playing();
+            },
+            PlayerCommand::SetSpeed(speed, tx) => {
+                let _ = tx.send(self.set_speed(speed).await);
+                self.refresh_now_playing();
+            },
+            PlayerCommand::Eq(change, tx) => {
+                let _ = tx.send(self.eq(change).await);
+            },
+            PlayerCommand::SetTransition(transition, tx) => {
+                let _ = tx.send(self.set_transition(transition).await);
+            },
+            PlayerCommand::TrackEnded(handle, errored) => self.track_ended(handle, errored).await,
+        }
+    }
+
+    async fn save_settings(&self){
+        self.store.save(self.guild_id, self.settings.clone()).await;
+    }
+
+    fn status(&self) -> PlayerStatus{
+        PlayerStatus{
+            song: self.current.as_ref().map(|now_playing| now_playing.song.clone()),
+            position: self.current.as_ref().map_or(Duration::from_secs(0), NowPlaying::position),
+            paused: self.current.as_ref().map_or(false, NowPlaying::is_paused),
+            loop_mode: self.loop_mode,
+            shuffle: self.shuffle,
+            up_next: if self.shuffle { None } else { self.queue.front().cloned() },
+            queue_len: self.queue.len(),
+            volume: self.settings.volume,
+            filter: self.settings.filter,
+            speed: self.settings.speed,
+        }
+    }
+
+    // Swaps the old now playing message out for a new one at the bottom of the channel. All the
+    // talking to discord happens off on its own, those are rate limited calls and the player
+    // shouldn't sit waiting on them. The new message's id comes back through posted_tx
+    fn post_now_playing(&mut self, channel: ChannelId){
+        self.clear_now_playing();
+        let status = self.status();
+        let post = self.now_playing_post;
+        let http = self.http.clone();
+        let tx = self.posted_tx.clone();
+        tokio::spawn(async move {
+            let posted = channel.send_message(&http, |m| {
+                m.embed(|e| {
+                    embed::now_playing_embed(e, &status);
+                    e
+                });
+
+                m
+            }).await;
+            let posted = match posted{
+                Ok(posted) => posted,
+                Err(err) => {
+                    error!("Failed to send the now playing message: {:?}", err);
+                    return;
+                },
+            };
+            let _ = tx.send((post, channel, posted.id));
+            for control in embed::CONTROLS{
+                if let Err(err) = channel.create_reaction(&http, posted.id, ReactionType::Unicode(control.to_string())).await{
+                    error!("Failed to add the now playing controls: {:?}", err);
+                    break;
+                }
+            }
+        });
+    }
+
+    // A now playing message finished sending, it's the one to keep up to date unless something
+    // else replaced it in the meantime
+    fn posted(&mut self, post: u64, channel: ChannelId, message_id: MessageId){
+        if post == self.now_playing_post{
+            self.now_playing_msg = Some((channel, message_id));
+        }else{
+            self.delete_message(channel, message_id);
+        }
+    }
+
+    // The edit happens off on its own with a snapshot of how things are right now
+    fn refresh_now_playing(&self){
+        if let Some((channel, message_id)) = self.now_playing_msg{
+            let status = self.status();
+            let http = self.http.clone();
+            tokio::spawn(async move {
+                let edit = channel.edit_message(&http, message_id, |m| {
+                    m.embed(|e| {
+                        embed::now_playing_embed(e, &status);
+                        e
+                    })
+                }).await;
+                if let Err(err) = edit{
+                    error!("Failed to update the now playing message: {:?}", err);
+                }
+            });
+        }
+    }
+
+    fn clear_now_playing(&mut self){
+        // anything still on its way is already out of date
+        self.now_playing_post += 1;
+        if let Some((channel, message_id)) = self.now_playing_msg.take(){
+            self.delete_message(channel, message_id);
+        }
+    }
+
+    // The delete happens off on its own, nothing here cares when it's done
+    fn delete_message(&self, channel: ChannelId, message_id: MessageId){
+        let http = self.http.clone();
+        tokio::spawn(async move {
+            if let Err(err) = channel.delete_message(&http, message_id).await{
+                error!("Failed to delete the old now playing message: {:?}", err);
+            }
+        });
+    }
+
+    // The reactions on the now playing message
+    async fn control(&mut self, message_id: MessageId, emoji: &str){
+        match self.now_playing_msg{
+            Some((_, np_id)) if np_id == message_id => {},
+            _ => return,
+        }
+        // these are buttons, if they don't do anything there's nobody to tell so just log it
+        let res = match emoji{
+            embed::PLAY_PAUSE => {
+                if self.current.as_ref().map_or(false, NowPlaying::is_paused){
+                    self.resume().await.map(|_| ())
+                }else{
+                    self.pause().map(|_| ())
+                }
+            },
+            embed::SKIP => self.skip().await.map(|_| ()),
+            embed::STOP => self.stop().await,
+            embed::SHUFFLE => {
+                self.shuffle = !self.shuffle;
+                Ok(())
+            },
+            _ => return,
+        };
+        if let Err(err) = res{
+            info!("Now playing control {} did nothing: {}", emoji, err);
+        }
+        self.refresh_now_playing();
+    }
+
+    // Makes sure a position from a user is actually in the queue
+    fn check_pos(&self, pos: usize) -> PlayerResult<usize>{
+        if pos == 0 || pos > self.queue.len(){
+            return Err(PlayerError::OutOfRange);
+        }
+        Ok(pos-1)
+    }
+
+    fn remove(&mut self, first: usize, last: usize) -> PlayerResult<Vec<SongInfo>>{
+        let start = self.check_pos(first)?;
+        let end = self.check_pos(last)?;
+        if start > end{
+            return Err(PlayerError::OutOfRange);
+        }
+        Ok(self.queue.drain(start..=end).collect())
+    }
+
+    fn move_song(&mut self, from: usize, to: usize) -> PlayerResult<SongInfo>{
+        let from = self.check_pos(from)?;
+        let to = self.check_pos(to)?;
+        let song = self.queue.remove(from).ok_or(PlayerError::OutOfRange)?;
+        self.queue.insert(to, song.clone());
+        Ok(song)
+    }
+
+    fn swap(&mut self, first: usize, second: usize) -> PlayerResult<(SongInfo, SongInfo)>{
+        let first = self.check_pos(first)?;
+        let second = self.check_pos(second)?;
+        self.queue.swap(first, second);
+        Ok((self.queue[first].clone(), self.queue[second].clone()))
+    }
+
+    // Plays the song right now in place of whatever was playing. The old song only gets retired once
+    // the new one is actually going, if it won't start the old one just keeps playing
+    async fn play(&mut self, song: SongInfo) -> PlayerResult<()>{
+        let old = self.current.as_ref().map(|now_playing| now_playing.song.clone());
+        self.start(song).await?;
+        if let Some(old) = old{
+            self.retire(old);
+        }
+        Ok(())
+    }
+
+    // Plays the song right now, whatever was playing before gets dropped
+    async fn start(&mut self, song: SongInfo) -> PlayerResult<()>{
+        let channel = song.channel;
+        self.load(song, Duration::from_secs(0)).await?;
+        self.post_now_playing(channel);
+        Ok(())
+    }
+
+    // Gets the song going from offset without saying anything about it
+    async fn load(&mut self, song: SongInfo, offset: Duration) -> PlayerResult<()>{
+        let call = self.call.clone().ok_or(PlayerError::NotConnected)?;
+        let (mut track, handle, stderr) = self.make_track(&song, offset)?;
+        // the volume goes on before it starts so there's no loud blip at the beginning
+        track.set_volume(volume_scale(self.settings.volume));
+        // play_only stops everything else, that includes a song fading out
+        call.lock().await.play_only(track);
+        self.fade = None;
+        self.gap_until = None;
+        self.retrying = None;
+        self.current = Some(NowPlaying::new(song, handle, stderr, offset, filters::playback_rate(&self.settings)));
+        Ok(())
+    }
+
+    // Uses the preloaded ffmpeg when it's the right song, starts a new one when it isn't
+    fn make_track(&mut self, song: &SongInfo, offset: Duration) -> PlayerResult<(SongbirdTrack, TrackHandle, StderrLog)>{
+        let filters = filters::filter_chain(&self.settings);
+        if offset == Duration::from_secs(0){
+            match self.preload.take(){
+                Some(Preload::Ready(warm)) if same_song(&warm.song, song) && warm.filters == filters => {
+                    info!("Using the preloaded {}", song.track.title);
+                    return Ok((warm.track, warm.handle, warm.stderr));
+                },
+                // still good for whatever is next
+                other => self.preload = other,
+            }
+        }
+        let (source, stderr) = make_source(&self.config.ffmpeg, song, offset, filters.as_deref()).map_err(|err| {
+            error!("Failed to start ffmpeg for {}: {:?}", song.track.title, err);
+            PlayerError::Source(song.track.title.clone(), "ffmpeg wouldn't start, go yell at Brandon".to_owned())
+        })?;
+        let (track, handle) = create_player(source);
+        Ok((track, handle, stderr))
+    }
+
+    // When transition_tick next has something to do, None when nothing's coming up. That's the end
+    // of a gap, the next step of a fade, the preload point or where a crossfade starts
+    fn next_deadline(&self) -> Option<Instant>{
+        if let Some(until) = self.gap_until{
+            return Some(until);
+        }
+        if self.fade.is_some(){
+            return Some(Instant::now() + TRANSITION_TICK);
+        }
+        // same checks check_preload and the crossfade go through, if they'd bail so does this
+        if self.call.is_none() || self.loop_mode == LoopMode::Track || self.queue.is_empty(){
+            return None;
+        }
+        let now_playing = match &self.current{
+            Some(now_playing) if !now_playing.is_paused() => now_playing,
+            _ => return None,
+        };
+        let left = now_playing.left()?;
+        let mut until = None;
+        if self.preload.is_none(){
+            until = Some(left.checked_sub(self.preload_ahead()).unwrap_or_default());
+        }
+        if let Some(fade) = self.crossfade_length(now_playing){
+            let fade_at = left.checked_sub(fade).unwrap_or_default();
+            until = Some(until.map_or(fade_at, |until: Duration| until.min(fade_at)));
+        }
+        until.map(|until| Instant::now() + until)
+    }
+
+    // How far ahead of the end the next song gets looked up, a crossfade needs it that much sooner
+    fn preload_ahead(&self) -> Duration{
+        match self.settings.transition{
+            Transition::Crossfade(secs) => PRELOAD_AHEAD + Duration::from_secs(secs),
+            _ => PRELOAD_AHEAD,
+        }
+    }
+
+    // How long the crossfade at the end of this song is, short songs get at most half of
+    // themselves faded. None when there isn't one
+    fn crossfade_length(&self, now_playing: &NowPlaying) -> Option<Duration>{
+        match self.settings.transition{
+            Transition::Crossfade(secs) => Some(Duration::from_secs(secs).min(now_playing.song.track.duration? / 2)),
+            _ => None,
+        }
+    }
+
+    // Runs whenever next_deadline comes up, starts the song after a gap, starts crossfades and
+    // moves them along
+    async fn transition_tick(&mut self){
+        self.drop_stale_preload();
+        self.check_preload();
+        if let Some(until) = self.gap_until{
+            if Instant::now() >= until{
+                self.gap_until = None;
+                self.advance().await;
+            }
+            return;
+        }
+        if self.fade.is_some(){
+            self.step_fade();
+            return;
+        }
+        // a looping song starts itself over and there's nothing to fade into with an empty queue
+        if self.loop_mode == LoopMode::Track || self.queue.is_empty(){
+            return;
+        }
+        let now_playing = match &self.current{
+            Some(now_playing) if !now_playing.is_paused() => now_playing,
+            _ => return,
+        };
+        // livestreams don't have an end to fade at
+        let (fade, left) = match (self.crossfade_length(now_playing), now_playing.left()){
+            (Some(fade), Some(left)) => (fade, left),
+            _ => return,
+        };
+        if left <= fade{
+            self.start_crossfade(fade).await;
+        }
+    }
+
+    // The queue got moved around and the preloaded song isn't next anymore, start over
+    fn drop_stale_preload(&mut self){
+        let stale = match &self.preload{
+            Some(preload) => self.queue.front().map_or(true, |next| !same_song(next, preload.song())),
+            None => false,
+        };
+        if stale{
+            self.preload = None;
+        }
+    }
+
+    // Gets the next song looked up again once the one playing is close to the end
+    fn check_preload(&mut self){
+        if self.preload.is_some() || self.loop_mode == LoopMode::Track || self.queue.is_empty(){
+            return;
+        }
+        let now_playing = match &self.current{
+            Some(now_playing) if !now_playing.is_paused() => now_playing,
+            _ => return,
+        };
+        // a livestream could go on forever, the next song gets done the old way when it's over
+        let left = match now_playing.left(){
+            Some(left) => left,
+            None => return,
+        };
+        if left > self.preload_ahead(){
+            return;
+        }
+        // shuffle picks the next song now instead of when it's needed so there's something to get ready
+        if self.shuffle{
+            let index = rand::thread_rng().gen_range(0..self.queue.len());
+            if let Some(song) = self.queue.remove(index){
+                self.queue.push_front(song);
+            }
+        }
+        let song = match self.queue.front(){
+            Some(song) => song.clone(),
+            None => return,
+        };
+        self.preload = Some(Preload::Pending(song.clone()));
+        self.look_up(Lookup::Preload, song);
+    }
+
+    // Runs the song back through the resolvers without holding anything up, what it finds comes
+    // back through refreshed_tx
+    fn look_up(&self, lookup: Lookup, song: SongInfo){
+        let resolvers = self.resolvers.clone();
+        let tx = self.refreshed_tx.clone();
+        tokio::spawn(async move {
+            let fresh = resolvers.refresh(&song.track).await;
+            let _ = tx.send((lookup, song, fresh));
+        });
+    }
+
+    // The new link for a song whose stream died came back, pick it up where it stopped
+    async fn retry(&mut self, mut song: SongInfo, position: Duration, fresh: Result<Track, ResolveError>){
+        match &self.retrying{
+            Some(waiting) if same_song(waiting, &song) => {},
+            // something else got played while we were waiting
+            _ => return,
+        }
+        self.retrying = None;
+        let res = match fresh{
+            Ok(track) => {
+                song.track = track;
+                // a livestream just starts over live
+                let position = if song.track.live { Duration::from_secs(0) } else { position };
+                self.load(song.clone(), position).await.map_err(|err| err.to_string())
+            },
+            Err(err) => Err(err.to_string()),
+        };
+        match res{
+            Ok(()) => {
+                if let Some(now_playing) = self.current.as_mut(){
+                    now_playing.retried = true;
+                }
+                info!("Got {} going again", song.track.title);
+                self.refresh_now_playing();
+            },
+            Err(err) => {
+                error!("Failed to get {} going again: {}", song.track.title, err);
+                self.give_up(song, Some(err)).await;
+            },
+        }
+    }
+
+    // Tells everyone the song's not working and moves on without it, why is whatever went wrong last
+    async fn give_up(&mut self, song: SongInfo, why: Option<String>){
+        let text = match why{
+            Some(why) => format!("Couldn't keep {} playing ({})", song.track.title, why),
+            None => format!("Couldn't keep {} playing, the link stopped working", song.track.title),
+        };
+        check_msg(song.channel.say(&self.http, &text).await);
+        self.history.push_front(song);
+        self.history.truncate(HISTORY_LEN);
+        self.advance().await;
+    }
+
+    // The lookup for the next song came back, get ffmpeg going on it
+    fn warm_up(&mut self, mut song: SongInfo, fresh: Result<Track, ResolveError>){
+        match &self.preload{
+            Some(Preload::Pending(waiting)) if same_song(waiting, &song) => {},
+            // it got used or thrown out while the lookup was going
+            _ => return,
+        }
+        match fresh{
+            Ok(track) => song.track = track,
+            // the old link might still work, it's worth a try
+            Err(err) => error!("Failed to look up {} again: {}", song.track.title, err),
+        }
+        // the queue gets the new link too so it's there even if the warm ffmpeg doesn't get used
+        if let Some(next) = self.queue.front_mut(){
+            if same_song(next, &song){
+                *next = song.clone();
+            }
+        }
+        let filters = filters::filter_chain(&self.settings);
+        let (source, stderr) = match make_source(&self.config.ffmpeg, &song, Duration::from_secs(0), filters.as_deref()){
+            Ok(made) => made,
+            Err(err) => {
+                error!("Failed to preload {}: {:?}", song.track.title, err);
+                return;
+            },
+        };
+        let (track, handle) = create_player(source);
+        info!("Preloaded {}", song.track.title);
+        self.preload = Some(Preload::Ready(Warm{
+            song: song,
+            track: track,
+            handle: handle,
+            stderr: stderr,
+            filters: filters,
+        }));
+    }
+
+    // Starts the next song quietly on top of the one that's ending, step_fade takes it from there
+    async fn start_crossfade(&mut self, length: Duration){
+        let call = match self.call.clone(){
+            Some(call) => call,
+            None => return,
+        };
+        let next_song = match self.next_song(){
+            Some(next_song) => next_song,
+            None => return,
+        };
+        let (mut track, handle, stderr) = match self.make_track(&next_song, Duration::from_secs(0)){
+            Ok(made) => made,
+            Err(err) => {
+                // it gets another go with whatever's after it on the next tick
+                error!("Failed to crossfade into the next song: {}", err);
+                check_msg(next_song.channel.say(&self.http, "Can't play the next queued song").await);
+                return;
+            },
+        };
+        track.set_volume(0.0);
+        // play instead of play_only so the old song keeps going underneath
+        call.lock().await.play(track);
+        let outgoing = match &self.current{
+            Some(now_playing) => now_playing.handle.clone(),
+            None => return,
+        };
+        self.retire_current();
+        let channel = next_song.channel;
+        self.current = Some(NowPlaying::new(next_song, handle, stderr, Duration::from_secs(0), filters::playback_rate(&self.settings)));
+        self.fade = Some(Fade{
+            outgoing: outgoing,
+            started: Instant::now(),
+            length: length,
+        });
+        self.post_now_playing(channel);
+    }
+
+    fn step_fade(&mut self){
+        let fade = match &self.fade{
+            Some(fade) => fade,
+            None => return,
+        };
+        let done = (fade.started.elapsed().as_secs_f64() / fade.length.as_secs_f64()).min(1.0);
+        let volume = volume_scale(self.settings.volume);
+        // these only fail when the track already finished on its own, nothing left to fade then
+        let _ = fade.outgoing.set_volume(volume * (1.0 - done) as f32);
+        if let Some(now_playing) = &self.current{
+            let _ = now_playing.handle.set_volume(volume * done as f32);
+        }
+        if done >= 1.0{
+            let _ = fade.outgoing.stop();
+            self.fade = None;
+        }
+    }
+
+    // Skips straight to the end of a crossfade
+    fn finish_fade(&mut self){
+        if let Some(fade) = self.fade.take(){
+            let _ = fade.outgoing.stop();
+            if let Some(now_playing) = &self.current{
+                let _ = now_playing.handle.set_volume(volume_scale(self.settings.volume));
+            }
+        }
+    }
+
+    async fn set_transition(&mut self, transition: Option<Transition>) -> PlayerResult<Transition>{
+        let transition = match transition{
+            Some(transition) => transition,
+            None => return Ok(self.settings.transition),
+        };
+        match transition{
+            Transition::Gap(secs) | Transition::Crossfade(secs) if secs > MAX_TRANSITION => return Err(PlayerError::TooLong),
+            _ => {},
+        }
+        self.settings.transition = transition;
+        self.save_settings().await;
+        Ok(transition)
+    }
+
+    // Jumps around in the song that's playing by starting ffmpeg over from the new spot. Gives back
+    // where it ended up
+    async fn seek(&mut self, target: SeekTarget) -> PlayerResult<Duration>{
+        let now_playing = self.current.as_ref().ok_or(PlayerError::NothingPlaying)?;
+        // there's nowhere to seek to in a livestream
+        if now_playing.song.track.live{
+            return Err(PlayerError::CantSeek);
+        }
+        let position = now_playing.position();
+        let to = match target{
+            SeekTarget::To(to) => to,
+            // a big enough number doesn't fit in a Duration, that's past the end of anything
+            SeekTarget::Forward(by) => position.checked_add(by).ok_or(PlayerError::PastTheEnd)?,
+            SeekTarget::Back(by) => position.checked_sub(by).unwrap_or_default(),
+        };
+        // a file that doesn't say how long it is gets the benefit of the doubt, ffmpeg just ends it
+        // if it's past the end
+        if now_playing.song.track.duration.map_or(false, |length| to >= length){
+            return Err(PlayerError::PastTheEnd);
+        }
+        let song = now_playing.song.clone();
+        let paused = now_playing.is_paused();
+        self.load(song, to).await?;
+        // it starts playing again from the new spot, a paused song should stay paused
+        if paused{
+            self.pause()?;
+        }
+        self.refresh_now_playing();
+        Ok(to)
+    }
+
+    // Changes the volume for this song and every one after it, the guild keeps it for next time
+    async fn set_volume(&mut self, volume: Option<u32>) -> PlayerResult<u32>{
+        let volume = match volume{
+            Some(volume) => volume,
+            None => return Ok(self.settings.volume),
+        };
+        if volume > self.config.max_volume{
+            return Err(PlayerError::TooLoud(self.config.max_volume));
+        }
+        if let Some(now_playing) = &self.current{
+            now_playing.handle.set_volume(volume_scale(volume))?;
+        }
+        self.settings.volume = volume;
+        self.save_settings().await;
+        Ok(volume)
+    }
+
+    // Starts ffmpeg over from where the song is so changes to the filters kick in right away
+    async fn reload_current(&mut self) -> PlayerResult<()>{
+        let now_playing = match &self.current{
+            Some(now_playing) => now_playing,
+            None => return Ok(()),
+        };
+        let song = now_playing.song.clone();
+        let paused = now_playing.is_paused();
+        // whatever got warmed up for the next song has the old filters, it gets done again
+        self.preload = None;
+        // a livestream can't seek, starting it over just picks it up live again
+        let position = if song.track.live { Duration::from_secs(0) } else { now_playing.position() };
+        self.load(song, position).await?;
+        if paused{
+            self.pause()?;
+        }
+        Ok(())
+    }
+
+    async fn set_normalize(&mut self, normalize: Option<bool>) -> PlayerResult<bool>{
+        self.settings.normalize = normalize.unwrap_or(!self.settings.normalize);
+        self.save_settings().await;
+        self.reload_current().await?;
+        Ok(self.settings.normalize)
+    }
+
+    // Applies to the song that's playing and everything after it
+    async fn set_filter(&mut self, filter: Option<FilterPreset>) -> PlayerResult<()>{
+        self.settings.filter = filter;
+        self.save_settings().await;
+        self.reload_current().await
+    }
+
+    async fn set_speed(&mut self, speed: f64) -> PlayerResult<()>{
+        if !(filters::MIN_SPEED..=filters::MAX_SPEED).contains(&speed){
+            return Err(PlayerError::BadSpeed);
+        }
+        self.settings.speed = speed;
+        self.save_settings().await;
+        self.reload_current().await
+    }
+
+    async fn eq(&mut self, change: EqChange) -> PlayerResult<EqState>{
+        let check_gain = |gain: f64| if gain.abs() <= filters::MAX_GAIN { Ok(gain) } else { Err(PlayerError::BadGain) };
+        let changed = !matches!(change, EqChange::Show);
+        // saving and showing don't change how it sounds so there's no need to restart the song
+        let reload = match change{
+            EqChange::Show => false,
+            EqChange::Band(band, gain) => {
+                if band == 0 || band > filters::EQ_BANDS.len(){
+                    return Err(PlayerError::BadBand);
+                }
+                self.settings.eq[band-1] = check_gain(gain)?;
+                true
+            },
+            EqChange::All(gains) => {
+                for gain in gains.iter(){
+                    check_gain(*gain)?;
+                }
+                self.settings.eq = gains;
+                true
+            },
+            EqChange::Reset => {
+                self.settings.eq = [0.0; 10];
+                true
+            },
+            EqChange::Save(name) => {
+                self.settings.eq_profiles.insert(name.to_lowercase(), self.settings.eq);
+                false
+            },
+            EqChange::Load(name) => {
+                self.settings.eq = *self.settings.eq_profiles.get(&name.to_lowercase()).ok_or(PlayerError::NoProfile(name))?;
+                true
+            },
+            EqChange::Delete(name) => {
+                self.settings.eq_profiles.remove(&name.to_lowercase()).ok_or(PlayerError::NoProfile(name))?;
+                false
+            },
+        };
+        if changed{
+            self.save_settings().await;
+        }
+        if reload{
+            self.reload_current().await?;
+        }
+        let mut profiles = self.settings.eq_profiles.keys().cloned().collect::<Vec<String>>();
+        profiles.sort();
+        Ok(EqState{
+            gains: self.settings.eq,
+            profiles: profiles,
+        })
+    }
+
+    // Takes the next song out of the queue, a random one when shuffle is on
+    fn next_song(&mut self) -> Option<SongInfo>{
+        // a preload already picked the random one and put it at the front
+        let picked = match (&self.preload, self.queue.front()){
+            (Some(preload), Some(next)) => same_song(preload.song(), next),
+            _ => false,
+        };
+        if self.shuffle && !self.queue.is_empty() && !picked{
+            let index = rand::thread_rng().gen_range(0..self.queue.len());
+            self.queue.remove(index)
+        }else{
+            self.queue.pop_front()
+        }
+    }
+
+    // The song that's playing is done with, it goes in the history and back on the end of the queue
+    // when the whole queue is looping
+    fn retire_current(&mut self){
+        if let Some(now_playing) = self.current.take(){
+            self.retire(now_playing.song);
+        }
+    }
+
+    fn retire(&mut self, song: SongInfo){
+        if self.loop_mode == LoopMode::Queue{
+            self.queue.push_back(song.clone());
+        }
+        self.history.push_front(song);
+        self.history.truncate(HISTORY_LEN);
+    }
+
+    // Keeps going through the queue until something plays, gives back what did
+    async fn play_next(&mut self) -> PlayerResult<Option<SongInfo>>{
+        while let Some(next_song) = self.next_song(){
+            match self.start(next_song.clone()).await{
+                Ok(()) => return Ok(Some(next_song)),
+                Err(PlayerError::Source(title, why)) => {
+                    error!("Failed to play the next song {}: {}", title, why);
+                    check_msg(next_song.channel.say(&self.http, "Can't play the next queued song").await);
+                },
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(None)
+    }
+
+    async fn skip(&mut self) -> PlayerResult<Option<SongInfo>>{
+        let call = self.call.clone().ok_or(PlayerError::NotConnected)?;
+        // skipping moves on even when the song is looping, but a looping queue keeps it
+        self.retire_current();
+        let next = self.play_next().await?;
+        if next.is_none(){
+            // ran out of songs, skipping the last one just stops it
+            call.lock().await.stop();
+            self.current = None;
+            self.clear_now_playing();
+        }
+        Ok(next)
+    }
+
+    async fn skip_to(&mut self, num: usize) -> PlayerResult<SongInfo>{
+        if self.call.is_none(){
+            return Err(PlayerError::NotConnected);
+        }
+        let index = self.check_pos(num)?;
+        // everything before the one we want gets skipped, it's taken straight off the front so
+        // shuffle doesn't get a say
+        let skipped = self.queue.drain(..index).collect::<Vec<SongInfo>>();
+        let song = self.queue.pop_front().ok_or(PlayerError::OutOfRange)?;
+        let old = self.current.as_ref().map(|now_playing| now_playing.song.clone());
+        if let Err(err) = self.start(song.clone()).await{
+            // put the queue back how it was, the old song never stopped
+            self.queue.push_front(song);
+            for skipped_song in skipped.into_iter().rev(){
+                self.queue.push_front(skipped_song);
+            }
+            return Err(err);
+        }
+        if let Some(old) = old{
+            self.retire(old);
+        }
+        // a looping queue comes back around to them, they go on the end after the old song
+        if self.loop_mode == LoopMode::Queue{
+            self.queue.extend(skipped);
+        }
+        Ok(song)
+    }
+
+    // Goes back to the last song, the one that was playing goes back on the front of the queue
+    async fn previous(&mut self) -> PlayerResult<SongInfo>{
+        if self.call.is_none(){
+            return Err(PlayerError::NotConnected);
+        }
+        let prev = self.history.pop_front().ok_or(PlayerError::NoHistory)?;
+        let old = self.current.as_ref().map(|now_playing| now_playing.song.clone());
+        if let Err(err) = self.start(prev.clone()).await{
+            // leave it where it was so they can try again, the old song never stopped
+            self.history.push_front(prev);
+            return Err(err);
+        }
+        // a looping queue already put it back on the end when it finished, it'll go there again
+        // when it finishes this time
+        if self.loop_mode == LoopMode::Queue{
+            if let Some(index) = self.queue.iter().rposition(|song| same_song(song, &prev)){
+                self.queue.remove(index);
+            }
+        }
+        if let Some(old) = old{
+            self.queue.push_front(old);
+        }
+        Ok(prev)
+    }
+
+    // Starts the song that's playing over from the beginning
+    async fn replay(&mut self) -> PlayerResult<SongInfo>{
+        let song = self.current.as_ref().ok_or(PlayerError::NothingPlaying)?.song.clone();
+        self.start(song.clone()).await?;
+        Ok(song)
+    }
+
+    fn pause(&mut self) -> PlayerResult<SongInfo>{
+        if self.current.as_ref().ok_or(PlayerError::NothingPlaying)?.is_paused(){
+            return Err(PlayerError::AlreadyPaused);
+        }
+        // the song on its way out shouldn't keep going while this one's paused
+        self.finish_fade();
+        let now_playing = self.current.as_mut().ok_or(PlayerError::NothingPlaying)?;
+        // pausing the track keeps its place, resume picks it back up from there
+        now_playing.handle.pause()?;
+        now_playing.pause();
+        Ok(now_playing.song.clone())
+    }
+
+    async fn resume(&mut self) -> PlayerResult<SongInfo>{
+        let now_playing = self.current.as_mut().ok_or(PlayerError::EmptyQueue)?;
+        if !now_playing.is_paused(){
+            return Err(PlayerError::AlreadyPlaying);
+        }
+        // the track is still sitting in songbird right where we left it, just unpause it
+        if let Err(err) = now_playing.handle.play(){
+            // the track is gone (probably got disconnected), the best we can do is start it over
+            error!("Failed to resume the track, restarting it: {:?}", err);
+            let song = now_playing.song.clone();
+            self.start(song.clone()).await?;
+            return Ok(song);
+        }
+        now_playing.resume();
+        Ok(now_playing.song.clone())
+    }
+
+    async fn stop(&mut self) -> PlayerResult<()>{
+        let call = self.call.take().ok_or(PlayerError::NotConnected)?;
+        let mut handler = call.lock().await;
+        handler.stop();
+        if let Err(err) = handler.leave().await{
+            error!("Failed to leave the voice channel: {:?}", err);
+        }
+        drop(handler);
+        self.fade = None;
+        self.gap_until = None;
+        self.preload = None;
+        self.retrying = None;
+        self.retire_current();
+        self.queue.clear();
+        self.clear_now_playing();
+        Ok(())
+    }
+
+    async fn track_ended(&mut self, ended: TrackHandle, errored: bool){
+        let (died, retried) = match &self.current{
+            // skip and friends replace the song themselves, the old one ending shouldn't skip again
+            Some(now_playing) if now_playing.handle.uuid() == ended.uuid() && !now_playing.is_paused() => {
+                (now_playing.died_early(errored), now_playing.retried)
+            },
+            _ => return,
+        };
+        if died{
+            if let Some(now_playing) = self.current.take(){
+                if retried{
+                    // ffmpeg usually says what went wrong, like a 403 from the server
+                    let why = now_playing.stderr.last();
+                    self.give_up(now_playing.song, why).await;
+                }else{
+                    // probably an expired link, get a new one and try again before saying anything
+                    info!("{} stopped early, looking it up again", now_playing.song.track.title);
+                    self.retrying = Some(now_playing.song.clone());
+                    self.look_up(Lookup::Retry(now_playing.position()), now_playing.song);
+                }
+            }
+            return;
+        }
+        if self.loop_mode == LoopMode::Track{
+            let song = self.current.as_ref().map(|now_playing| now_playing.song.clone());
+            if let Some(song) = song{
+                match self.start(song).await{
+                    Ok(()) => return,
+                    // if it won't play again there's no point looping it, move on instead
+                    Err(err) => error!("Failed to loop the song: {}", err),
+                }
+            }
+        }
+        self.retire_current();
+        if let Transition::Gap(secs) = self.settings.transition{
+            if secs > 0 && !self.queue.is_empty(){
+                // transition_tick picks it up when the gap's over
+                self.gap_until = Some(Instant::now() + Duration::from_secs(secs));
+                return;
+            }
+        }
+        self.advance().await;
+    }
+
+    // Moves on to whatever's next once the last song is done and out of the way
+    async fn advance(&mut self){
+        match self.play_next().await{
+            Ok(Some(_)) => {},
+            Ok(None) => {
+                info!("Queue ran out for guild {}", self.guild_id);
+                self.current = None;
+                self.clear_now_playing();
+            },
+            Err(err) => {
+                error!("Failed to move on to the next song: {}", err);
+                self.current = None;
+                self.clear_now_playing();
+            },
+        }
+    }
+}
+
+// songbird wants 1.0 for normal volume, we keep it as a percent
+fn volume_scale(volume: u32) -> f32{
+    volume as f32 / 100.0
+}
+
+// What the commands hold on to, cloning it is cheap and every clone talks to the same player
+#[derive(Clone)]
+pub struct PlayerHandle{
+    tx: mpsc::UnboundedSender<PlayerCommand>,
+}
+
+impl PlayerHandle{
+    fn spawn(guild_id: GuildId, http: Arc<Http>, config: Arc<Config>, store: Arc<SettingsStore>, resolvers: Arc<Resolvers>) -> PlayerHandle{
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (refreshed_tx, refreshed_rx) = mpsc::unbounded_channel();
+        let (posted_tx, posted_rx) = mpsc::unbounded_channel();
+        let player = GuildPlayer{
+            guild_id: guild_id,
+            http: http,
+            config: config,
+            store: store,
+            resolvers: resolvers,
+            settings: GuildSettings::default(),
+            call: None,
+            queue: VecDeque::new(),
+            current: None,
+            history: VecDeque::new(),
+            shuffle: false,
+            loop_mode: LoopMode::Off,
+            now_playing_msg: None,
+            now_playing_post: 0,
+            posted_tx: posted_tx,
+            fade: None,
+            gap_until: None,
+            preload: None,
+            retrying: None,
+            refreshed_tx: refreshed_tx,
+        };
+        tokio::spawn(player.run(rx, refreshed_rx, posted_rx));
+        PlayerHandle{
+            tx: tx,
+        }
+    }
+
+    async fn request<T>(&self, make_cmd: impl FnOnce(oneshot::Sender<T>) -> PlayerCommand) -> PlayerResult<T>{
+        let (tx, rx) = oneshot::channel();
+        self.tx.send(make_cmd(tx)).map_err(|_| PlayerError::Gone)?;
+        rx.await.map_err(|_| PlayerError::Gone)
+    }
+
+    // Hands the player the voice connection and gets told when its tracks end
+    pub async fn attach(&self, call: Arc<Mutex<Call>>){
+        let mut handler = call.lock().await;
+        // the call sticks around after leaving so clear out the old notifier before adding a new one
+        handler.remove_all_global_events();
+        handler.add_global_event(Event::Track(TrackEvent::End), TrackEndNotifier{ player: self.clone(), errored: false });
+        handler.add_global_event(Event::Track(TrackEvent::Error), TrackEndNotifier{ player: self.clone(), errored: true });
+        drop(handler);
+        if self.tx.send(PlayerCommand::Attach(call)).is_err(){
+            error!("Tried to attach a call to a player that's gone");
+        }
+    }
+
+    pub async fn play(&self, song: SongInfo) -> PlayerResult<()>{
+        self.request(|tx| PlayerCommand::Play(song, tx)).await?
+    }
+
+    // Gives back how long the queue is now
+    pub async fn enqueue(&self, song: SongInfo) -> PlayerResult<usize>{
+        self.request(|tx| PlayerCommand::Enqueue(song, tx)).await
+    }
+
+    // Gives back the song that's playing now, None if the queue ran out
+    pub async fn skip(&self) -> PlayerResult<Option<SongInfo>>{
+        self.request(PlayerCommand::Skip).await?
+    }
+
+    pub async fn skip_to(&self, num: usize) -> PlayerResult<SongInfo>{
+        self.request(|tx| PlayerCommand::SkipTo(num, tx)).await?
+    }
+
+    pub async fn pause(&self) -> PlayerResult<SongInfo>{
+        self.request(PlayerCommand::Pause).await?
+    }
+
+    pub async fn resume(&self) -> PlayerResult<SongInfo>{
+        self.request(PlayerCommand::Resume).await?
+    }
+
+    pub async fn stop(&self) -> PlayerResult<()>{
+        self.request(PlayerCommand::Stop).await?
+    }
+
+    pub async fn queue(&self) -> PlayerResult<Vec<SongInfo>>{
+        self.request(PlayerCommand::Queue).await
+    }
+
+    pub async fn status(&self) -> PlayerResult<PlayerStatus>{
+        self.request(PlayerCommand::Status).await
+    }
+
+    // Goes back to the last song that played, gives back what's playing now
+    pub async fn previous(&self) -> PlayerResult<SongInfo>{
+        self.request(PlayerCommand::Previous).await?
+    }
+
+    // Starts the song that's playing over, gives back what that was
+    pub async fn replay(&self) -> PlayerResult<SongInfo>{
+        self.request(PlayerCommand::Replay).await?
+    }
+
+    // The songs that played most recently, newest first
+    pub async fn history(&self) -> PlayerResult<Vec<SongInfo>>{
+        self.request(PlayerCommand::History).await
+    }
+
+    // Gives back where in the song it ended up
+    pub async fn seek(&self, target: SeekTarget) -> PlayerResult<Duration>{
+        self.request(|tx| PlayerCommand::Seek(target, tx)).await?
+    }
+
+    // Sets the volume in percent, or just asks what it is with None. Gives back the volume it's at now
+    pub async fn set_volume(&self, volume: Option<u32>) -> PlayerResult<u32>{
+        self.request(|tx| PlayerCommand::SetVolume(volume, tx)).await?
+    }
+
+    // Turns loudness normalization on or off, or flips it with None. Gives back whether it's on now
+    pub async fn set_normalize(&self, normalize: Option<bool>) -> PlayerResult<bool>{
+        self.request(|tx| PlayerCommand::SetNormalize(normalize, tx)).await?
+    }
+
+    // Puts a filter on everything that plays, None takes it off
+    pub async fn set_filter(&self, filter: Option<FilterPreset>) -> PlayerResult<()>{
+        self.request(|tx| PlayerCommand::SetFilter(filter, tx)).await?
+    }
+
+    // 1.0 is normal speed
+    pub async fn set_speed(&self, speed: f64) -> PlayerResult<()>{
+        self.request(|tx| PlayerCommand::SetSpeed(speed, tx)).await?
+    }
+
+    // Changes the equalizer, gives back what it's set to after
+    pub async fn eq(&self, change: EqChange) -> PlayerResult<EqState>{
+        self.request(|tx| PlayerCommand::Eq(change, tx)).await?
+    }
+
+    // Sets what happens between songs, or just asks with None. Gives back what it is now
+    pub async fn set_transition(&self, transition: Option<Transition>) -> PlayerResult<Transition>{
+        self.request(|tx| PlayerCommand::SetTransition(transition, tx)).await?
+    }
+
+    // Posts the now playing message with the controls on it in that channel
+    pub async fn show_now_playing(&self, channel: ChannelId) -> PlayerResult<()>{
+        self.request(|tx| PlayerCommand::ShowNowPlaying(channel, tx)).await?
+    }
+
+    // Passes on a reaction to the player, it works out if it's one of the now playing controls
+    pub fn control(&self, message_id: MessageId, emoji: String){
+        if self.tx.send(PlayerCommand::Control(message_id, emoji)).is_err(){
+            error!("Tried to use the controls on a player that's gone");
+        }
+    }
+
+    // Puts the song at that spot in the queue, pushing everything after it back. Gives back how long
+    // the queue is now
+    pub async fn insert(&self, pos: usize, song: SongInfo) -> PlayerResult<usize>{
+        self.request(|tx| PlayerCommand::Insert(pos, song, tx)).await
+    }
+
+    // Takes out everything from first to last, both included
+    pub async fn remove(&self, first: usize, last: usize) -> PlayerResult<Vec<SongInfo>>{
+        self.request(|tx| PlayerCommand::Remove(first, last, tx)).await?
+    }
+
+    pub async fn move_song(&self, from: usize, to: usize) -> PlayerResult<SongInfo>{
+        self.request(|tx| PlayerCommand::Move(from, to, tx)).await?
+    }
+
+    pub async fn swap(&self, first: usize, second: usize) -> PlayerResult<(SongInfo, SongInfo)>{
+        self.request(|tx| PlayerCommand::Swap(first, second, tx)).await?
+    }
+
+    // Mixes up the queue once, gives back how many songs got mixed
+    pub async fn shuffle(&self) -> PlayerResult<usize>{
+        self.request(PlayerCommand::Shuffle).await
+    }
+
+    // Turns shuffle mode on or off, or flips it with None. Gives back whether it's on now
+    pub async fn set_shuffle(&self, shuffle: Option<bool>) -> PlayerResult<bool>{
+        self.request(|tx| PlayerCommand::SetShuffle(shuffle, tx)).await
+    }
+
+    // Sets the loop mode, or moves on to the next one with None. Gives back the mode it's in now
+    pub async fn set_loop(&self, mode: Option<LoopMode>) -> PlayerResult<LoopMode>{
+        self.request(|tx| PlayerCommand::SetLoop(mode, tx)).await
+    }
+
+    // Empties the queue without touching what's playing, gives back how many songs got cleared
+    pub async fn clear(&self) -> PlayerResult<usize>{
+        self.request(PlayerCommand::Clear).await
+    }
+}
+
+// Songbird calls this whenever a track on a guild's voice connection ends or errors out, it's what
+// moves the queue along to the next song
+struct TrackEndNotifier{
+    player: PlayerHandle,
+    // which of the two events this one is listening for
+    errored: bool,
+}
+
+#[async_trait]
+impl VoiceEventHandler for TrackEndNotifier{
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event>{
+        if let EventContext::Track(track_list) = ctx{
+            for (_state, handle) in track_list.iter(){
+                if self.player.tx.send(PlayerCommand::TrackEnded((*handle).clone(), self.errored)).is_err(){
+                    // the player's gone, no point hearing about anything else
+                    return Some(Event::Cancel);
+                }
+            }
+        }
+        // None keeps the event registered on the call
+        None
+    }
+}
+
+// Every guild's player, made the first time something asks for one
+pub struct Players{
+    http: Arc<Http>,
+    config: Arc<Config>,
+    store: Arc<SettingsStore>,
+    resolvers: Arc<Resolvers>,
+    players: RwLock<HashMap<GuildId, PlayerHandle>>,
+}
+
+impl Players{
+    pub fn new(http: Arc<Http>, config: Arc<Config>, store: Arc<SettingsStore>, resolvers: Arc<Resolvers>) -> Players{
+        Players{
+            http: http,
+            config: config,
+            store: store,
+            resolvers: resolvers,
+            players: RwLock::new(HashMap::new()),
+        }
+    }
+
+    // Only gives back a player the guild already has
+    pub async fn find(&self, guild_id: GuildId) -> Option<PlayerHandle>{
+        self.players.read().await.get(&guild_id).cloned()
+    }
+
+    pub async fn get(&self, guild_id: GuildId) -> PlayerHandle{
+        if let Some(player) = self.players.read().await.get(&guild_id){
+            return player.clone();
+        }
+        self.players.write().await
+            .entry(guild_id)
+            .or_insert_with(|| PlayerHandle::spawn(guild_id, self.http.clone(), self.config.clone(), self.store.clone(), self.resolvers.clone()))
+            .clone()
+    }
+}
+
+pub struct PlayerContainer;
+
+impl TypeMapKey for PlayerContainer{
+    type Value = Arc<Players>;
+}
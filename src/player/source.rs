@@ -0,0 +1,90 @@
+use std::{
+    io,
+    process::{
+        Command,
+        Stdio,
+    },
+    time::Duration,
+};
+
+use songbird::{
+    input::{
+        Codec,
+        Container,
+        Input,
+        child::{
+            children_to_reader,
+        },
+    },
+};
+
+use crate::{
+    player::SongInfo,
+    process::{
+        spawn_std,
+        StderrLog,
+    },
+};
+
+// start is how far into the song to begin, ffmpeg skips straight there. filters is an -af chain
+// for ffmpeg to run the audio through, see filters.rs. Whatever ffmpeg complains about ends up in
+// the log that comes back with it
+pub fn make_source(ffmpeg_path: &str, data: &SongInfo, start: Duration, filters: Option<&str>) -> io::Result<(Input, StderrLog)>{
+    let mut ffmpeg = Command::new(ffmpeg_path);
+    if start > Duration::from_secs(0){
+        // before the -i so ffmpeg seeks the input instead of decoding everything up to that point
+        ffmpeg.arg("-ss").arg(format!("{:.3}", start.as_secs_f64()));
+    }
+    let url = &data.track.stream_url;
+    if url.starts_with("http://") || url.starts_with("https://"){
+        // a connection that stops sending anything errors out after 15 seconds (it's in
+        // microseconds) instead of hanging forever, then the player can get a fresh link
+        ffmpeg.args(&["-rw_timeout", "15000000"]);
+        // livestreams and radio go on for hours, a dropped connection should pick back up instead
+        // of ending the song. These only mean anything for http so files don't get them
+        if data.track.live{
+            ffmpeg.args(&[
+                "-reconnect",
+                "1",
+                "-reconnect_streamed",
+                "1",
+                "-reconnect_delay_max",
+                "5",
+            ]);
+        }
+    }
+    ffmpeg.arg("-i").arg(url);
+    if let Some(filters) = filters{
+        ffmpeg.arg("-af").arg(filters);
+    }
+    // This actually runs in the background and feeds data to the websocket, that's pretty cool
+    ffmpeg
+        .args(&[
+            // just the actual errors, those go to stderr where spawn_std picks them up
+            "-loglevel",
+            "error",
+            "-hide_banner",
+            "-f",
+            "s16le",// THIS IS AN L NOT A 1, THIS FUCKING FONT
+            "-ac",
+            "2",
+            "-ar",
+            "48000",
+            "-acodec",
+            "pcm_f32le", // this if f32 little edian because that's what songbird needs it to be
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped());
+    // songbird owns the child from here, it waits on it once the track's done with
+    let (ffmpeg, stderr) = spawn_std("ffmpeg", &mut ffmpeg)?;
+    let metadata = data.track.metadata();
+
+    Ok((Input::new(
+            true, // It's stereo
+            children_to_reader::<f32>(vec![ffmpeg]), // This is the actual data from the ffmpeg program running in the background
+            Codec::FloatPcm, //this is the codec we put in the up above
+            Container::Raw, // IT'S FOOKIN RAW
+            Some(metadata), // metadata taken from the track
+    ), stderr))
+}
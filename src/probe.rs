@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::process::Command;
+use tracing::error;
+
+use crate::process::output;
+
+// a file that takes longer than this to look at probably isn't going to play either
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+// What ffprobe could tell us about a file, tags are whatever the file happened to have in it
+#[derive(Clone, Debug, Default)]
+pub struct Probe{
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+// Asks ffprobe about a file or a link, None if ffprobe couldn't make sense of it
+pub async fn probe(ffprobe: &str, input: &str) -> Option<Probe>{
+    let mut cmd = Command::new(ffprobe);
+    cmd.args(&[
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_format",
+        ])
+        .arg(input);
+    let output = match output("ffprobe", &mut cmd, PROBE_TIMEOUT).await{
+        Ok(output) => output,
+        Err(err) => {
+            error!("ffprobe couldn't read {}: {}", input, err);
+            return None;
+        },
+    };
+    let value: Value = serde_json::from_slice(&output.stdout).ok()?;
+    let format = value.get("format")?;
+    // every format spells its tags differently, flac likes TITLE and mp3 likes title
+    let tag = |name: &str| {
+        format.get("tags")?.as_object()?.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, val)| val.as_str())
+            .map(|val| val.trim().to_owned())
+            .filter(|val| !val.is_empty())
+    };
+    Some(Probe{
+        title: tag("title"),
+        artist: tag("artist"),
+        // ffprobe hands the duration back as a string for some reason
+        duration: format.get("duration")
+            .and_then(Value::as_str)
+            .and_then(|secs| secs.parse::<f64>().ok())
+            .filter(|secs| secs.is_finite() && *secs > 0.0)
+            .map(Duration::from_secs_f64),
+    })
+}
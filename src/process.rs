@@ -0,0 +1,205 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    error::Error,
+    io::{
+        self,
+        BufRead,
+        BufReader,
+    },
+    process::{
+        Child as StdChild,
+        Command as StdCommand,
+        Output,
+        Stdio,
+    },
+    sync::{
+        Arc,
+        Mutex as StdMutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use tokio::{
+    io::{
+        AsyncBufReadExt,
+        BufReader as AsyncBufReader,
+    },
+    process::{
+        Child,
+        Command,
+    },
+    task::JoinHandle,
+    time,
+};
+
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+
+// how many lines of stderr get kept around to tell people what went wrong
+const STDERR_LINES: usize = 10;
+// how long the startup checks give each program to say what version it is
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+// how long the stderr reader gets to catch up once the program's exited
+const STDERR_DRAIN: Duration = Duration::from_secs(1);
+
+// The last few things a program said on stderr. Everything it says gets logged as well, this is
+// just so the end of it can go in error messages
+#[derive(Clone, Default)]
+pub struct StderrLog{
+    lines: Arc<StdMutex<VecDeque<String>>>,
+}
+
+impl StderrLog{
+    fn push(&self, line: String){
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == STDERR_LINES{
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    // The last thing it said, that's usually the actual error
+    pub fn last(&self) -> Option<String>{
+        self.lines.lock().unwrap().back().cloned()
+    }
+}
+
+#[derive(Debug)]
+pub enum ProcessError{
+    // usually means the program isn't installed or the path in the config is wrong
+    Spawn(io::Error),
+    Io(io::Error),
+    TimedOut,
+    // it ran and said no, holds what it said on stderr or the exit status if it didn't say anything
+    Failed(String),
+}
+
+impl fmt::Display for ProcessError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            ProcessError::Spawn(err) => write!(f, "couldn't start it: {}", err),
+            ProcessError::Io(err) => write!(f, "couldn't talk to it: {}", err),
+            ProcessError::TimedOut => write!(f, "it took too long and got killed"),
+            ProcessError::Failed(why) => write!(f, "{}", why),
+        }
+    }
+}
+
+impl Error for ProcessError{}
+
+// Starts a program the std way, songbird wants std children for the audio. stderr gets read on its
+// own thread, which finishes by itself when the program exits
+pub fn spawn_std(name: &'static str, cmd: &mut StdCommand) -> io::Result<(StdChild, StderrLog)>{
+    let mut child = cmd.stderr(Stdio::piped()).spawn()?;
+    let log = StderrLog::default();
+    if let Some(stderr) = child.stderr.take(){
+        let log = log.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().filter_map(|line| line.ok()){
+                warn!("{}: {}", name, line);
+                log.push(line);
+            }
+        });
+    }
+    Ok((child, log))
+}
+
+// Starts a program the tokio way, it gets killed if the child is dropped so nothing is left running
+// after whoever started it gives up. stderr gets read by its own task, that's the handle that comes
+// back with it so finish can wait for the last of it
+pub fn spawn(name: &'static str, cmd: &mut Command) -> io::Result<(Child, StderrLog, JoinHandle<()>)>{
+    let mut child = cmd
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let log = StderrLog::default();
+    let stderr = child.stderr.take();
+    let reader_log = log.clone();
+    let reader = tokio::spawn(async move {
+        let stderr = match stderr{
+            Some(stderr) => stderr,
+            None => return,
+        };
+        let mut lines = AsyncBufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await{
+            warn!("{}: {}", name, line);
+            reader_log.push(line);
+        }
+    });
+    Ok((child, log, reader))
+}
+
+// Waits for a program to exit, killing it if it goes past the timeout. Either way it's reaped by the
+// time this returns
+pub async fn finish(name: &'static str, child: &mut Child, log: &StderrLog, reader: JoinHandle<()>, timeout: Duration) -> Result<(), ProcessError>{
+    let status = match time::timeout(timeout, child.wait()).await{
+        Ok(status) => status.map_err(ProcessError::Io)?,
+        Err(_) => {
+            error!("{} took longer than {:?}, killing it", name, timeout);
+            let _ = child.start_kill();
+            if let Err(err) = child.wait().await{
+                error!("Failed to clean up {}: {:?}", name, err);
+            }
+            return Err(ProcessError::TimedOut);
+        },
+    };
+    if status.success(){
+        return Ok(());
+    }
+    // the program's gone but its last words might still be on their way through the reader, and
+    // those are usually the actual error
+    if time::timeout(STDERR_DRAIN, reader).await.is_err(){
+        error!("Gave up waiting on the rest of what {} said", name);
+    }
+    Err(ProcessError::Failed(log.last().unwrap_or_else(|| status.to_string())))
+}
+
+// Runs a program start to finish and gives back what it printed
+pub async fn output(name: &'static str, cmd: &mut Command, timeout: Duration) -> Result<Output, ProcessError>{
+    let child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(ProcessError::Spawn)?;
+    // dropping the output future drops the child, kill_on_drop takes care of it from there
+    let output = match time::timeout(timeout, child.wait_with_output()).await{
+        Ok(output) => output.map_err(ProcessError::Io)?,
+        Err(_) => {
+            error!("{} took longer than {:?}, killing it", name, timeout);
+            return Err(ProcessError::TimedOut);
+        },
+    };
+    if !output.status.success(){
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let why = stderr.lines().rev()
+            .find(|line| !line.trim().is_empty())
+            .map(|line| line.trim().to_owned())
+            .unwrap_or_else(|| output.status.to_string());
+        return Err(ProcessError::Failed(why));
+    }
+    Ok(output)
+}
+
+// Makes sure every program the bot runs is actually there before anyone asks for a song, saves
+// finding out halfway through someone's playlist
+pub async fn check_binaries(config: &Config){
+    let checks = [
+        ("ffmpeg", config.ffmpeg.as_str(), "-version"),
+        ("ffprobe", config.ffprobe.as_str(), "-version"),
+        ("youtube-dl", config.youtube_dl.binary.as_str(), "--version"),
+    ];
+    for &(name, binary, flag) in checks.iter(){
+        match output(name, Command::new(binary).arg(flag), CHECK_TIMEOUT).await{
+            Ok(output) => {
+                let version = String::from_utf8_lossy(&output.stdout);
+                info!("Using {} at {}: {}", name, binary, version.lines().next().unwrap_or("").trim());
+            },
+            Err(err) => error!("{} at {} isn't going to work, {}", name, binary, err),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use serenity::model::{
+    channel::{
+        Attachment,
+        Message,
+    },
+    id::UserId,
+};
+
+use crate::{
+    probe::probe,
+    resolver::{
+        http::is_audio_file,
+        TrackStream,
+    },
+    track::Track,
+};
+
+// Audio files dropped straight into the chat, discord's link goes to ffmpeg like any other file
+pub async fn attachment_track(ffprobe: &str, attachment: &Attachment, requester: UserId) -> Track{
+    let mut track = Track::new(attachment.filename.clone(), attachment.url.clone(), requester);
+    track.webpage_url = Some(attachment.url.clone());
+    track.extractor = Some("attachment".to_owned());
+    // discord doesn't tell us how long it is, ffprobe has to go and look
+    if let Some(probed) = probe(ffprobe, &attachment.url).await{
+        if let Some(title) = probed.title{
+            track.title = title;
+        }
+        track.uploader = probed.artist;
+        track.duration = probed.duration;
+    }
+    track
+}
+
+// The audio attached to the message, or to the message it's replying to when it doesn't have any
+// of its own. None when neither has any
+pub async fn message_tracks(ffprobe: &str, msg: &Message) -> Option<TrackStream>{
+    let audio = |msg: &Message| msg.attachments.iter()
+        .filter(|attachment| is_audio_file(&attachment.filename))
+        .cloned()
+        .collect::<Vec<Attachment>>();
+    let mut attachments = audio(msg);
+    if attachments.is_empty(){
+        attachments = msg.referenced_message.as_deref().map(audio).unwrap_or_default();
+    }
+    if attachments.is_empty(){
+        return None;
+    }
+    let mut tracks = Vec::with_capacity(attachments.len());
+    for attachment in attachments.iter(){
+        // whoever ran the command asked for it, even if someone else uploaded it
+        tracks.push(attachment_track(ffprobe, attachment, msg.author.id).await);
+    }
+    Some(TrackStream::from_tracks(tracks))
+}
@@ -0,0 +1,142 @@
+use serenity::model::id::UserId;
+
+use crate::{
+    probe::probe,
+    resolver::{
+        host,
+        scheme,
+        Resolver,
+        ResolveError,
+        TrackStream,
+    },
+    track::Track,
+};
+
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3",
+    "ogg",
+    "oga",
+    "opus",
+    "flac",
+    "wav",
+    "m4a",
+    "aac",
+    "webm",
+];
+
+// Sites youtube-dl has a proper extractor for. Their links go to it even when they look like a
+// file, it knows where the real audio is and what the song is called
+const YTDL_HOSTS: &[&str] = &[
+    "youtube.com",
+    "youtu.be",
+    "soundcloud.com",
+    "bandcamp.com",
+    "vimeo.com",
+];
+
+// "m.youtube.com" and "artist.bandcamp.com" count as well as the plain name
+fn ytdl_host(host: &str) -> bool{
+    let host = host.to_lowercase();
+    YTDL_HOSTS.iter().any(|site| host == *site || host.ends_with(&format!(".{}", site)))
+}
+
+// "song.MP3" gives back true
+pub fn is_audio_file(name: &str) -> bool{
+    match name.rfind('.'){
+        Some(dot) => {
+            let ext = name[dot+1..].to_lowercase();
+            AUDIO_EXTENSIONS.contains(&ext.as_str())
+        },
+        None => false,
+    }
+}
+
+// Links straight to an audio file, ffmpeg can read those without youtube-dl getting involved
+pub struct HttpAudio{
+    ffprobe: String,
+}
+
+impl HttpAudio{
+    pub fn new(ffprobe: String) -> HttpAudio{
+        HttpAudio{
+            ffprobe: ffprobe,
+        }
+    }
+}
+
+impl Resolver for HttpAudio{
+    fn name(&self) -> &'static str{
+        "http"
+    }
+
+    fn handles(&self, query: &str) -> bool{
+        match scheme(query){
+            Some("http") | Some("https") => {},
+            _ => return false,
+        }
+        if host(query).map_or(false, ytdl_host){
+            return false;
+        }
+        // ignore the query string and fragment, they don't say anything about the file
+        let path = query.split(|c| c == '?' || c == '#').next().unwrap_or(query);
+        is_audio_file(path)
+    }
+
+    fn resolve(&self, query: &str, requester: UserId) -> Result<TrackStream, ResolveError>{
+        let path = query.split(|c| c == '?' || c == '#').next().unwrap_or(query);
+        // the file name is the closest thing to a title we've got
+        let title = path.rsplit('/').next().unwrap_or(path).to_owned();
+        let mut track = Track::new(title, query.to_owned(), requester);
+        track.webpage_url = Some(query.to_owned());
+        track.extractor = Some("http".to_owned());
+        // the link doesn't say how long the file is, ffprobe has to go and look
+        let (tx, stream) = TrackStream::channel();
+        let ffprobe = self.ffprobe.clone();
+        tokio::spawn(async move {
+            if let Some(probed) = probe(&ffprobe, &track.stream_url).await{
+                if let Some(title) = probed.title{
+                    track.title = title;
+                }
+                track.uploader = probed.artist;
+                track.duration = probed.duration;
+            }
+            let _ = tx.send(Ok(track)).await;
+        });
+        Ok(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn http() -> HttpAudio{
+        HttpAudio::new("ffprobe".to_owned())
+    }
+
+    #[test]
+    fn audio_files(){
+        assert!(is_audio_file("song.mp3"));
+        assert!(is_audio_file("Song.FLAC"));
+        assert!(is_audio_file("some.dotted.name.opus"));
+        assert!(!is_audio_file("song.mp3.txt"));
+        assert!(!is_audio_file("mp3"));
+        assert!(!is_audio_file("song."));
+    }
+
+    #[test]
+    fn handles_file_links(){
+        assert!(http().handles("https://example.com/music/song.mp3"));
+        assert!(http().handles("http://example.com/song.ogg?token=abc#t=5"));
+        assert!(!http().handles("https://example.com/page.html"));
+        assert!(!http().handles("ftp://example.com/song.mp3"));
+        assert!(!http().handles("song.mp3"));
+    }
+
+    #[test]
+    fn leaves_known_sites_to_youtube_dl(){
+        assert!(!http().handles("https://www.youtube.com/watch.mp3"));
+        assert!(!http().handles("https://artist.bandcamp.com/track/song.mp3"));
+        assert!(http().handles("https://notyoutube.com/song.mp3"));
+    }
+}
@@ -0,0 +1,379 @@
+use std::{
+    fs,
+    io,
+    path::{
+        Component,
+        Path,
+        PathBuf,
+    },
+    sync::{
+        Arc,
+        RwLock,
+    },
+    time::Duration,
+};
+
+use serenity::{
+    model::id::UserId,
+    prelude::TypeMapKey,
+};
+use tracing::{error, info};
+
+use crate::{
+    probe::probe,
+    resolver::{
+        http::is_audio_file,
+        scheme,
+        Resolver,
+        ResolveError,
+        TrackStream,
+    },
+    track::Track,
+};
+
+// One file in the library and what its tags say about it
+#[derive(Clone, Debug)]
+pub struct LibraryEntry{
+    pub path: PathBuf,
+    pub title: String,
+    pub artist: Option<String>,
+    pub duration: Option<Duration>,
+    // whether ffprobe has had a look at it yet
+    pub probed: bool,
+}
+
+impl LibraryEntry{
+    // Just the file name for a title, for files that haven't been looked at yet
+    fn bare(path: &Path) -> LibraryEntry{
+        let title = path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string_lossy().into_owned());
+        LibraryEntry{
+            path: path.to_owned(),
+            title: title,
+            artist: None,
+            duration: None,
+            probed: false,
+        }
+    }
+
+    // Fills in whatever the file's tags say
+    async fn read_tags(&mut self, ffprobe: &str){
+        if let Some(probed) = probe(ffprobe, &self.path.to_string_lossy()).await{
+            if let Some(title) = probed.title{
+                self.title = title;
+            }
+            self.artist = probed.artist;
+            self.duration = probed.duration;
+        }
+        self.probed = true;
+    }
+
+    pub fn track(&self, requester: UserId) -> Track{
+        let mut track = Track::new(self.title.clone(), self.path.to_string_lossy().into_owned(), requester);
+        track.uploader = self.artist.clone();
+        track.duration = self.duration;
+        track.extractor = Some("local".to_owned());
+        track
+    }
+
+    // every word has to show up somewhere in the artist, title or path. Only the path inside the
+    // library counts, otherwise the folders the library sits in would match everything
+    fn matches(&self, words: &[String], root: &Path) -> bool{
+        let path = self.path.strip_prefix(root).unwrap_or(&self.path);
+        let haystack = format!("{} {} {}", self.artist.as_deref().unwrap_or(""), self.title, path.to_string_lossy()).to_lowercase();
+        words.iter().all(|word| haystack.contains(word.as_str()))
+    }
+}
+
+// The folder of music on the bot's host, everything played out of it has to be inside it
+pub struct LocalLibrary{
+    root: PathBuf,
+    // filled in by scan, songs just get their file names as titles until their tags have been read
+    entries: RwLock<Vec<LibraryEntry>>,
+    // what reads the tags
+    ffprobe: String,
+}
+
+impl LocalLibrary{
+    // Starts reading the tags of everything in the folder in the background
+    pub fn open(root: PathBuf, ffprobe: String) -> io::Result<Arc<LocalLibrary>>{
+        let library = Arc::new(LocalLibrary{
+            // canonical so the starts_with check below can't be fooled by ".." or symlinks
+            root: root.canonicalize()?,
+            entries: RwLock::new(Vec::new()),
+            ffprobe: ffprobe,
+        });
+        tokio::spawn(library.clone().scan());
+        Ok(library)
+    }
+
+    // Goes through every audio file under the root and asks ffprobe what's in its tags
+    pub async fn scan(self: Arc<Self>){
+        let root = self.root.clone();
+        let files = match tokio::task::spawn_blocking(move || audio_files(&root)).await{
+            Ok(files) => files,
+            Err(err) => {
+                error!("Failed to look through the local library: {:?}", err);
+                return;
+            },
+        };
+        // everything goes in by file name first so searching works straight away, the tags get
+        // filled in one at a time as ffprobe gets to them
+        let mut entries = files.iter().map(|file| LibraryEntry::bare(file)).collect::<Vec<LibraryEntry>>();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        info!("Found {} songs in the local library, reading their tags", entries.len());
+        *self.entries.write().unwrap() = entries.clone();
+        for (index, mut entry) in entries.into_iter().enumerate(){
+            entry.read_tags(&self.ffprobe).await;
+            if let Some(slot) = self.entries.write().unwrap().get_mut(index){
+                *slot = entry;
+            }
+        }
+        info!("Done reading the local library's tags");
+    }
+
+    // Turns a path from a user into a real path, as long as it stays inside the library
+    fn library_path(&self, query: &str) -> Result<PathBuf, ResolveError>{
+        let relative = Path::new(query.trim_start_matches("file://").trim_start_matches('/'));
+        // ".." could lead anywhere, and looking to see would tell people what's on the host
+        if relative.components().any(|part| !matches!(part, Component::Normal(_) | Component::CurDir)){
+            return Err(ResolveError::OutsideLibrary(query.to_owned()));
+        }
+        let path = self.root.join(relative);
+        // a symlinked folder can still lead out. The deepest part of the path that's actually
+        // there decides, so whether the rest exists outside the library never makes a difference
+        let mut existing = path.as_path();
+        let real = loop{
+            if let Ok(real) = existing.canonicalize(){
+                break real;
+            }
+            existing = match existing.parent(){
+                Some(parent) => parent,
+                None => return Err(ResolveError::NotFound(query.to_owned())),
+            };
+        };
+        if !real.starts_with(&self.root){
+            return Err(ResolveError::OutsideLibrary(query.to_owned()));
+        }
+        if existing != path{
+            return Err(ResolveError::NotFound(query.to_owned()));
+        }
+        Ok(real)
+    }
+
+    // The real path with symlinks and ".." worked out, None if that isn't inside the library
+    fn inside(&self, path: &Path) -> Option<PathBuf>{
+        let path = path.canonicalize().ok()?;
+        if path.starts_with(&self.root){
+            Some(path)
+        }else{
+            None
+        }
+    }
+
+    // The audio files right in dir in name order, the same check as a single file keeps anything
+    // that leads out of the library out of the list
+    fn folder(&self, dir: &Path) -> io::Result<Vec<PathBuf>>{
+        let mut files = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| self.inside(&entry.path()))
+            .filter(|file| file.is_file() && is_audio_file(&file.to_string_lossy()))
+            .collect::<Vec<PathBuf>>();
+        files.sort();
+        Ok(files)
+    }
+
+    // What the index knows about the file, or just its name if the scan hasn't got to it
+    fn entry(&self, path: &Path) -> LibraryEntry{
+        self.entries.read().unwrap().iter()
+            .find(|entry| entry.path == path)
+            .cloned()
+            .unwrap_or_else(|| LibraryEntry::bare(path))
+    }
+
+    // A file plays on its own, a folder plays like a playlist in name order
+    pub fn resolve(&self, query: &str, requester: UserId) -> Result<TrackStream, ResolveError>{
+        let path = self.library_path(query)?;
+        let files = if path.is_dir(){
+            self.folder(&path).map_err(ResolveError::Io)?
+        }else{
+            vec![path]
+        };
+        if files.is_empty(){
+            return Err(ResolveError::NotFound(query.to_owned()));
+        }
+        Ok(self.tracks(files.iter().map(|file| self.entry(file)).collect(), requester))
+    }
+
+    // Turns entries into tracks, anything the scan hasn't got to yet gets its tags read first so
+    // it has a length to play with
+    pub fn tracks(&self, entries: Vec<LibraryEntry>, requester: UserId) -> TrackStream{
+        let (tx, stream) = TrackStream::channel();
+        let ffprobe = self.ffprobe.clone();
+        tokio::spawn(async move {
+            for mut entry in entries{
+                if !entry.probed{
+                    entry.read_tags(&ffprobe).await;
+                }
+                if tx.send(Ok(entry.track(requester))).await.is_err(){
+                    break;
+                }
+            }
+        });
+        stream
+    }
+
+    // Everything whose tags or path have all the words in them, in path order
+    pub fn search(&self, query: &str, limit: usize) -> Vec<LibraryEntry>{
+        let words = query.split_whitespace().map(str::to_lowercase).collect::<Vec<String>>();
+        if words.is_empty(){
+            return Vec::new();
+        }
+        self.entries.read().unwrap().iter()
+            .filter(|entry| entry.matches(&words, &self.root))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+// Every audio file under dir, symlinked folders get skipped so a loop can't keep it going forever
+fn audio_files(dir: &Path) -> Vec<PathBuf>{
+    let mut files = Vec::new();
+    let entries = match fs::read_dir(dir){
+        Ok(entries) => entries,
+        Err(err) => {
+            error!("Can't read {:?} in the local library: {:?}", dir, err);
+            return files;
+        },
+    };
+    for entry in entries.filter_map(|entry| entry.ok()){
+        let path = entry.path();
+        match entry.file_type(){
+            Ok(kind) if kind.is_dir() => files.extend(audio_files(&path)),
+            Ok(kind) if kind.is_file() && is_audio_file(&path.to_string_lossy()) => files.push(path),
+            _ => {},
+        }
+    }
+    files
+}
+
+pub struct LocalLibraryContainer;
+
+impl TypeMapKey for LocalLibraryContainer{
+    type Value = Arc<LocalLibrary>;
+}
+
+// Plays file:// links out of the library
+pub struct LocalFile{
+    library: Arc<LocalLibrary>,
+}
+
+impl LocalFile{
+    pub fn new(library: Arc<LocalLibrary>) -> LocalFile{
+        LocalFile{
+            library: library,
+        }
+    }
+}
+
+impl Resolver for LocalFile{
+    fn name(&self) -> &'static str{
+        "local"
+    }
+
+    fn handles(&self, query: &str) -> bool{
+        scheme(query) == Some("file")
+    }
+
+    fn resolve(&self, query: &str, requester: UserId) -> Result<TrackStream, ResolveError>{
+        self.library.resolve(query, requester)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use std::{
+        env,
+        process,
+    };
+
+    use super::*;
+
+    // A throwaway library in the temp folder with a song in it, a folder, and a song outside it.
+    // Gives back the library and the folder holding all of that
+    fn library(name: &str) -> (LocalLibrary, PathBuf){
+        let base = env::temp_dir().join(format!("maestro-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let root = base.join("music");
+        fs::create_dir_all(root.join("album")).unwrap();
+        fs::write(root.join("song.mp3"), b"").unwrap();
+        fs::write(root.join("album").join("one.mp3"), b"").unwrap();
+        fs::write(base.join("secret.mp3"), b"").unwrap();
+        let library = LocalLibrary{
+            root: root.canonicalize().unwrap(),
+            entries: RwLock::new(Vec::new()),
+            ffprobe: "ffprobe".to_owned(),
+        };
+        (library, base)
+    }
+
+    #[test]
+    fn paths_inside_the_library(){
+        let (library, base) = library("inside");
+        let song = library.root.join("song.mp3");
+        assert_eq!(library.library_path("song.mp3").unwrap(), song);
+        assert_eq!(library.library_path("/song.mp3").unwrap(), song);
+        assert_eq!(library.library_path("file://song.mp3").unwrap(), song);
+        assert_eq!(library.library_path("file:///song.mp3").unwrap(), song);
+        assert_eq!(library.library_path("./album/one.mp3").unwrap(), library.root.join("album").join("one.mp3"));
+        assert!(matches!(library.library_path("nope.mp3"), Err(ResolveError::NotFound(_))));
+        let _ = fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn paths_outside_the_library(){
+        let (library, base) = library("outside");
+        assert!(matches!(library.library_path("../secret.mp3"), Err(ResolveError::OutsideLibrary(_))));
+        assert!(matches!(library.library_path("file://../secret.mp3"), Err(ResolveError::OutsideLibrary(_))));
+        assert!(matches!(library.library_path("album/../song.mp3"), Err(ResolveError::OutsideLibrary(_))));
+        // there or not, it gets the same answer so nobody can go looking around the host
+        assert!(matches!(library.library_path("../missing.mp3"), Err(ResolveError::OutsideLibrary(_))));
+        assert!(matches!(library.library_path("../../../../../../etc/passwd"), Err(ResolveError::OutsideLibrary(_))));
+        assert!(matches!(library.library_path("../../../../../../etc/missing"), Err(ResolveError::OutsideLibrary(_))));
+        // an absolute path still gets looked for inside the library
+        let absolute = base.join("secret.mp3");
+        assert!(matches!(library.library_path(&absolute.to_string_lossy()), Err(ResolveError::NotFound(_))));
+        let _ = fs::remove_dir_all(base);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_out_of_the_library(){
+        use std::os::unix::fs::symlink;
+
+        let (library, base) = library("symlink");
+        symlink(base.join("secret.mp3"), library.root.join("escape.mp3")).unwrap();
+        symlink(base.join("secret.mp3"), library.root.join("album").join("escape.mp3")).unwrap();
+        assert!(matches!(library.library_path("escape.mp3"), Err(ResolveError::OutsideLibrary(_))));
+        // a folder linked from outside gives the same answer for what is and isn't in it
+        symlink(&base, library.root.join("out")).unwrap();
+        assert!(matches!(library.library_path("out/secret.mp3"), Err(ResolveError::OutsideLibrary(_))));
+        assert!(matches!(library.library_path("out/missing.mp3"), Err(ResolveError::OutsideLibrary(_))));
+        // the same goes for one sitting in a folder that gets played
+        let album = library.library_path("album").unwrap();
+        assert_eq!(library.folder(&album).unwrap(), vec![album.join("one.mp3")]);
+        let _ = fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn search_ignores_where_the_library_is(){
+        let (library, base) = library("search");
+        let entry = LibraryEntry::bare(&library.root.join("album").join("one.mp3"));
+        let words = |query: &str| query.split_whitespace().map(str::to_owned).collect::<Vec<String>>();
+        assert!(entry.matches(&words("album one"), &library.root));
+        assert!(!entry.matches(&words("music"), &library.root));
+        let _ = fs::remove_dir_all(base);
+    }
+}
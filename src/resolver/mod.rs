@@ -0,0 +1,311 @@
+use std::{
+    fmt,
+    error::Error,
+    io,
+    sync::{
+        Arc,
+    },
+};
+
+use serenity::{
+    model::id::UserId,
+    prelude::*,
+};
+
+use tokio::sync::mpsc;
+use tracing::info;
+
+use crate::{
+    process::ProcessError,
+    track::{
+        Track,
+        TrackError,
+    },
+};
+
+pub mod youtube_dl;
+pub mod http;
+pub mod local;
+pub mod attachment;
+
+pub use youtube_dl::YoutubeDl;
+pub use http::HttpAudio;
+pub use local::{
+    LocalFile,
+    LocalLibrary,
+    LocalLibraryContainer,
+};
+
+// A playlist can be hundreds of songs long so they get handed out one at a time as they come in,
+// the resolver keeps working in the background while the first ones get played
+pub struct TrackStream{
+    rx: mpsc::Receiver<Result<Track, ResolveError>>,
+}
+
+impl TrackStream{
+    // For resolvers that find their tracks a bit at a time, send them down the sender as they show
+    // up. Sending fails once nobody is listening anymore, that's the cue to stop
+    pub fn channel() -> (mpsc::Sender<Result<Track, ResolveError>>, TrackStream){
+        let (tx, rx) = mpsc::channel(16);
+        (tx, TrackStream{
+            rx: rx,
+        })
+    }
+
+    // For resolvers that already know every track up front
+    pub fn from_tracks(tracks: Vec<Track>) -> TrackStream{
+        let (tx, rx) = mpsc::channel(tracks.len().max(1));
+        for track in tracks{
+            // can't fail, there's room for all of them and we're still holding the receiver
+            let _ = tx.try_send(Ok(track));
+        }
+        TrackStream{
+            rx: rx,
+        }
+    }
+
+    pub async fn next(&mut self) -> Option<Result<Track, ResolveError>>{
+        self.rx.recv().await
+    }
+}
+
+pub trait Resolver: Send + Sync{
+    // short name that shows up in the logs
+    fn name(&self) -> &'static str;
+    // whether this resolver wants the query, they get asked in the order they were added
+    fn handles(&self, query: &str) -> bool;
+    // turns a url or search into the tracks it points to
+    fn resolve(&self, query: &str, requester: UserId) -> Result<TrackStream, ResolveError>;
+    // the top results for a search, for resolvers that can search at all
+    fn search(&self, query: &str, _limit: usize, _requester: UserId) -> Result<TrackStream, ResolveError>{
+        Err(ResolveError::Unsupported(query.to_owned()))
+    }
+}
+
+#[derive(Debug)]
+pub enum ResolveError{
+    Unsupported(String),
+    NotFound(String),
+    OutsideLibrary(String),
+    Spawn(io::Error),
+    Io(io::Error),
+    // the resolver ran but it didn't work out, what it said is in there
+    Process(ProcessError),
+    Track(TrackError),
+}
+
+impl fmt::Display for ResolveError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            ResolveError::Unsupported(query) => write!(f, "nothing knows how to play '{}'", query),
+            ResolveError::NotFound(query) => write!(f, "couldn't find '{}'", query),
+            ResolveError::OutsideLibrary(query) => write!(f, "'{}' is outside the music library", query),
+            ResolveError::Spawn(err) => write!(f, "failed to start the resolver: {}", err),
+            ResolveError::Io(err) => write!(f, "failed to read from the resolver: {}", err),
+            ResolveError::Process(err) => write!(f, "{}", err),
+            ResolveError::Track(err) => write!(f, "bad track info: {}", err),
+        }
+    }
+}
+
+impl Error for ResolveError{}
+
+impl From<TrackError> for ResolveError{
+    fn from(err: TrackError) -> ResolveError{
+        ResolveError::Track(err)
+    }
+}
+
+// Every resolver the bot knows about, checked in order so the catch-all one goes last
+pub struct Resolvers{
+    resolvers: Vec<Box<dyn Resolver>>,
+}
+
+impl Resolvers{
+    pub fn new() -> Resolvers{
+        Resolvers{
+            resolvers: Vec::new(),
+        }
+    }
+
+    pub fn with<R: Resolver + 'static>(mut self, resolver: R) -> Resolvers{
+        self.resolvers.push(Box::new(resolver));
+        self
+    }
+
+    pub fn pick(&self, query: &str) -> Option<&dyn Resolver>{
+        self.resolvers.iter()
+            .find(|resolver| resolver.handles(query))
+            .map(|resolver| resolver.as_ref())
+    }
+
+    pub fn resolve(&self, query: &str, requester: UserId) -> Result<TrackStream, ResolveError>{
+        let resolver = self.pick(query).ok_or_else(|| ResolveError::Unsupported(query.to_owned()))?;
+        info!("Resolving '{}' with {}", query, resolver.name());
+        resolver.resolve(query, requester)
+    }
+
+    // Looks a track up again from the page it came from, the stream links sites like youtube hand
+    // out stop working after a few hours. Tracks that play straight from their link come back as is
+    pub async fn refresh(&self, track: &Track) -> Result<Track, ResolveError>{
+        let page = match &track.webpage_url{
+            Some(page) if *page != track.stream_url => page,
+            _ => return Ok(track.clone()),
+        };
+        let mut tracks = self.resolve(page, track.requester)?;
+        let mut fresh = match tracks.next().await{
+            Some(res) => res?,
+            None => return Err(ResolveError::NotFound(page.clone())),
+        };
+        // it's still the same request, it just has a new link
+        fresh.requester = track.requester;
+        fresh.added_at = track.added_at;
+        // a radio station the resolver doesn't know is live still is
+        fresh.live |= track.live;
+        Ok(fresh)
+    }
+
+    // asks each resolver in turn until one of them can search
+    pub fn search(&self, query: &str, limit: usize, requester: UserId) -> Result<TrackStream, ResolveError>{
+        for resolver in self.resolvers.iter(){
+            match resolver.search(query, limit, requester){
+                Err(ResolveError::Unsupported(_)) => continue,
+                res => {
+                    info!("Searching '{}' with {}", query, resolver.name());
+                    return res;
+                },
+            }
+        }
+        Err(ResolveError::Unsupported(query.to_owned()))
+    }
+}
+
+pub struct ResolverContainer;
+
+impl TypeMapKey for ResolverContainer{
+    type Value = Arc<Resolvers>;
+}
+
+// Anything with a scheme on the front counts as a link, everything else is something to search for
+pub fn is_url(query: &str) -> bool{
+    scheme(query).is_some()
+}
+
+// "https://example.com/a.mp3" gives back "https". It has to be right at the start and look like a
+// scheme, "song name https://..." is a search that happens to have a link in it
+pub fn scheme(query: &str) -> Option<&str>{
+    let end = query.find("://")?;
+    let scheme = &query[..end];
+    let mut chars = scheme.chars();
+    let first = chars.next()?;
+    if first.is_ascii_alphabetic() && chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.'){
+        Some(scheme)
+    }else{
+        None
+    }
+}
+
+// "https://example.com/a.mp3" gives back "example.com"
+pub fn host(query: &str) -> Option<&str>{
+    let rest = &query[scheme(query)?.len() + 3..];
+    let end = rest.find(|c| c == '/' || c == '?' || c == '#').unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+#[cfg(test)]
+mod tests{
+    use std::time::{
+        Duration,
+        UNIX_EPOCH,
+    };
+
+    use super::*;
+
+    // Takes anything starting with its prefix and gives back one track for it, the stream link is
+    // the query with "/stream" on the end so a refresh can be told apart from the original
+    struct Fake{
+        name: &'static str,
+        prefix: &'static str,
+    }
+
+    impl Resolver for Fake{
+        fn name(&self) -> &'static str{
+            self.name
+        }
+
+        fn handles(&self, query: &str) -> bool{
+            query.starts_with(self.prefix)
+        }
+
+        fn resolve(&self, query: &str, requester: UserId) -> Result<TrackStream, ResolveError>{
+            let mut track = Track::new(format!("{} {}", self.name, query), format!("{}/stream", query), requester);
+            track.webpage_url = Some(query.to_owned());
+            Ok(TrackStream::from_tracks(vec![track]))
+        }
+    }
+
+    fn resolvers() -> Resolvers{
+        Resolvers::new()
+            .with(Fake{name: "files", prefix: "file://"})
+            .with(Fake{name: "web", prefix: "https://"})
+            .with(Fake{name: "anything", prefix: ""})
+    }
+
+    #[test]
+    fn pick_goes_in_order(){
+        let resolvers = resolvers();
+        assert_eq!(resolvers.pick("file:///music/a.mp3").map(|r| r.name()), Some("files"));
+        assert_eq!(resolvers.pick("https://example.com").map(|r| r.name()), Some("web"));
+        assert_eq!(resolvers.pick("some song").map(|r| r.name()), Some("anything"));
+        assert!(Resolvers::new().pick("some song").is_none());
+    }
+
+    #[tokio::test]
+    async fn resolve_uses_the_picked_resolver(){
+        let mut tracks = resolvers().resolve("https://example.com", UserId(1)).unwrap();
+        let track = tracks.next().await.unwrap().unwrap();
+        assert_eq!(track.title, "web https://example.com");
+        assert!(tracks.next().await.is_none());
+
+        let res = Resolvers::new().with(Fake{name: "web", prefix: "https://"}).resolve("some song", UserId(1));
+        assert!(matches!(res, Err(ResolveError::Unsupported(_))));
+    }
+
+    #[tokio::test]
+    async fn refresh_gets_a_new_link_for_the_same_request(){
+        let mut track = Track::new("old".to_owned(), "https://cdn.example.com/expired".to_owned(), UserId(7));
+        track.webpage_url = Some("https://example.com/song".to_owned());
+        track.added_at = UNIX_EPOCH + Duration::from_secs(1000);
+
+        let fresh = resolvers().refresh(&track).await.unwrap();
+        assert_eq!(fresh.stream_url, "https://example.com/song/stream");
+        assert_eq!(fresh.requester, UserId(7));
+        assert_eq!(fresh.added_at, track.added_at);
+    }
+
+    #[tokio::test]
+    async fn refresh_leaves_direct_links_alone(){
+        let mut track = Track::new("file".to_owned(), "https://example.com/a.mp3".to_owned(), UserId(7));
+        track.webpage_url = Some(track.stream_url.clone());
+        // nothing to resolve with, it'd fail if it tried
+        let same = Resolvers::new().refresh(&track).await.unwrap();
+        assert_eq!(same.stream_url, track.stream_url);
+    }
+
+    #[test]
+    fn schemes_and_hosts(){
+        assert_eq!(scheme("https://example.com/a.mp3"), Some("https"));
+        assert_eq!(scheme("file:///music/a.mp3"), Some("file"));
+        assert_eq!(scheme("just some words"), None);
+        assert_eq!(scheme("song name https://example.com"), None);
+        assert_eq!(scheme(" https://example.com"), None);
+        assert_eq!(scheme("://example.com"), None);
+        assert_eq!(host("https://example.com/a.mp3"), Some("example.com"));
+        assert_eq!(host("https://example.com?q=1"), Some("example.com"));
+        assert_eq!(host("https://example.com"), Some("example.com"));
+        assert_eq!(host("just some words"), None);
+        assert!(is_url("https://example.com"));
+        assert!(!is_url("just some words"));
+        assert!(!is_url("song name https://example.com"));
+    }
+}
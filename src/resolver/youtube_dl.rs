@@ -0,0 +1,183 @@
+use std::{
+    process::Stdio,
+    time::Duration,
+};
+
+use tokio::{
+    io::{
+        AsyncBufReadExt,
+        BufReader,
+    },
+    process::Command,
+    time,
+};
+
+use serenity::model::id::UserId;
+use tracing::error;
+
+use crate::{
+    config::YoutubeDlConfig,
+    process::{
+        self,
+        ProcessError,
+    },
+    resolver::{
+        is_url,
+        Resolver,
+        ResolveError,
+        TrackStream,
+    },
+    track::Track,
+};
+
+// a big playlist takes a while, but this long without a single song means it's stuck
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+// how long it gets to exit once it's printed everything
+const EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Anything youtube-dl (or yt-dlp, they take the same flags) can make sense of, which is most things
+pub struct YoutubeDl{
+    config: YoutubeDlConfig,
+}
+
+impl YoutubeDl{
+    pub fn new(config: YoutubeDlConfig) -> YoutubeDl{
+        YoutubeDl{
+            config: config,
+        }
+    }
+
+    fn run(&self, target: &str, requester: UserId) -> Result<TrackStream, ResolveError>{
+        let mut cmd = Command::new(&self.config.binary);
+        cmd.args(&[
+                "-f",
+                "webm[abr>0]/bestaudio/best",
+                "--print-json",
+                "--skip-download",
+                //"--newline",
+            ])
+            // logins come from the config, if there aren't any we go without
+            .args(self.config.auth_args())
+            // keeps a query starting with a dash from being read as a flag
+            .arg("--")
+            .arg(target)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped());
+        // if whoever asked stops listening youtube-dl goes away with the task
+        let (mut child, stderr, reader) = process::spawn("youtube-dl", &mut cmd).map_err(ResolveError::Spawn)?;
+
+        // youtube-dl prints one json object per line, one line for each song in a playlist
+        let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+        let (tx, stream) = TrackStream::channel();
+        let target = target.to_owned();
+        tokio::spawn(async move {
+            let mut found = 0;
+            // whether it got to the end of its output on its own
+            let mut finished = false;
+            loop{
+                let res = match time::timeout(IDLE_TIMEOUT, lines.next_line()).await{
+                    Ok(Ok(Some(line))) => Track::from_ytdl_json(&line, requester).map_err(ResolveError::from),
+                    Ok(Ok(None)) => {
+                        finished = true;
+                        break;
+                    },
+                    Ok(Err(err)) => Err(ResolveError::Io(err)),
+                    Err(_) => {
+                        error!("youtube-dl stopped answering on {}", target);
+                        let _ = tx.send(Err(ResolveError::Process(ProcessError::TimedOut))).await;
+                        break;
+                    },
+                };
+                if res.is_ok(){
+                    found += 1;
+                }
+                if tx.send(res).await.is_err(){
+                    // nobody wants the rest of the playlist
+                    break;
+                }
+            }
+            if finished{
+                match process::finish("youtube-dl", &mut child, &stderr, reader, EXIT_TIMEOUT).await{
+                    Ok(()) => {},
+                    // a playlist with a few missing videos fails at the end, the rest still got through
+                    Err(err) if found > 0 => error!("youtube-dl had trouble with part of {}: {}", target, err),
+                    Err(err) => {
+                        error!("youtube-dl failed on {}: {}", target, err);
+                        let _ = tx.send(Err(ResolveError::Process(err))).await;
+                    },
+                }
+            }else{
+                // make sure it's gone and reaped
+                let _ = child.start_kill();
+                if let Err(err) = child.wait().await{
+                    error!("Failed to clean up youtube-dl: {:?}", err);
+                }
+            }
+        });
+        Ok(stream)
+    }
+}
+
+impl Resolver for YoutubeDl{
+    fn name(&self) -> &'static str{
+        "youtube-dl"
+    }
+
+    fn handles(&self, _query: &str) -> bool{
+        // this is the catch-all, it gets whatever the others didn't want
+        true
+    }
+
+    fn resolve(&self, query: &str, requester: UserId) -> Result<TrackStream, ResolveError>{
+        if is_url(query) || is_search(query){
+            self.run(query, requester)
+        }else{
+            // plain words get searched for and the best match played
+            self.run(&format!("ytsearch1:{}", query), requester)
+        }
+    }
+
+    fn search(&self, query: &str, limit: usize, requester: UserId) -> Result<TrackStream, ResolveError>{
+        self.run(&format!("ytsearch{}:{}", limit, query), requester)
+    }
+}
+
+// the sites youtube-dl can search, "yt" is youtube, "sc" soundcloud and so on
+const SEARCH_SITES: &[&str] = &[
+    "yt",
+    "sc",
+    "gv",
+    "bili",
+    "nico",
+];
+
+// youtube-dl's own search prefixes look like "ytsearch:", "scsearch5:" or "ytsearchall:", let
+// those through untouched. Anything else with a colon in it is just a search that has one
+fn is_search(query: &str) -> bool{
+    let prefix = match query.find(':'){
+        Some(colon) => &query[..colon],
+        None => return false,
+    };
+    SEARCH_SITES.iter().any(|site| {
+        match prefix.strip_prefix(site).and_then(|rest| rest.strip_prefix("search")){
+            Some(count) => count.is_empty() || count == "all" || count.chars().all(|c| c.is_ascii_digit()),
+            None => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn search_prefixes(){
+        assert!(is_search("ytsearch:some song"));
+        assert!(is_search("scsearch5:some song"));
+        assert!(is_search("ytsearchall:some song"));
+        assert!(!is_search("research: the movie"));
+        assert!(!is_search("ytsearch five:some song"));
+        assert!(!is_search("song title: the remix"));
+        assert!(!is_search("no colon at all"));
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serenity::{
+    model::id::GuildId,
+    prelude::*,
+};
+use tracing::{error, info};
+
+use crate::player::{
+    Transition,
+    filters::{
+        EqGains,
+        FilterPreset,
+    },
+};
+
+// Everything a guild has set up for itself, it survives the bot restarting
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildSettings{
+    // percent, 100 is however loud the song is to begin with
+    pub volume: u32,
+    // run everything through loudnorm so quiet and loud uploads come out about the same
+    pub normalize: bool,
+    pub filter: Option<FilterPreset>,
+    // how fast songs play without changing the pitch, 1.0 is normal
+    pub speed: f64,
+    // dB for each of the equalizer's bands, all zero is flat
+    pub eq: EqGains,
+    // equalizer settings people saved to come back to later
+    pub eq_profiles: HashMap<String, EqGains>,
+    // what happens between one song and the next
+    pub transition: Transition,
+}
+
+impl Default for GuildSettings{
+    fn default() -> GuildSettings{
+        GuildSettings{
+            volume: 100,
+            normalize: false,
+            filter: None,
+            speed: 1.0,
+            eq: [0.0; 10],
+            eq_profiles: HashMap::new(),
+            transition: Transition::Gapless,
+        }
+    }
+}
+
+// "settings.json" with ".bak" comes out as "settings.json.bak"
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf{
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// Every guild's settings, written out to a json file whenever one of them changes
+pub struct SettingsStore{
+    path: PathBuf,
+    // keyed by the plain number, json only does string keys and this turns into those cleanly
+    guilds: Mutex<HashMap<u64, GuildSettings>>,
+}
+
+impl SettingsStore{
+    pub fn load(path: PathBuf) -> SettingsStore{
+        let guilds = match fs::read_to_string(&path){
+            Ok(text) => match serde_json::from_str(&text){
+                Ok(guilds) => guilds,
+                Err(err) => {
+                    // moved out of the way instead of thrown out, the next save would write over
+                    // every other guild's settings
+                    let backup = with_suffix(&path, ".bak");
+                    error!("Settings file {:?} is broken, moving it to {:?} and starting over: {}", path, backup, err);
+                    if let Err(err) = fs::rename(&path, &backup){
+                        error!("Failed to move the broken settings file out of the way: {}", err);
+                    }
+                    HashMap::new()
+                },
+            },
+            // no file yet is fine, it gets made the first time something changes
+            Err(_) => {
+                info!("No settings file at {:?}, starting fresh", path);
+                HashMap::new()
+            },
+        };
+        SettingsStore{
+            path: path,
+            guilds: Mutex::new(guilds),
+        }
+    }
+
+    pub async fn get(&self, guild_id: GuildId) -> GuildSettings{
+        self.guilds.lock().await.get(&guild_id.0).cloned().unwrap_or_default()
+    }
+
+    pub async fn save(&self, guild_id: GuildId, settings: GuildSettings){
+        let mut guilds = self.guilds.lock().await;
+        guilds.insert(guild_id.0, settings);
+        // written next to it and then swapped in, so dying halfway through leaves the old file alone
+        let temp = with_suffix(&self.path, ".tmp");
+        let res = serde_json::to_string_pretty(&*guilds)
+            .map_err(|err| err.to_string())
+            .and_then(|text| fs::write(&temp, text).map_err(|err| err.to_string()))
+            .and_then(|_| fs::rename(&temp, &self.path).map_err(|err| err.to_string()));
+        if let Err(err) = res{
+            error!("Failed to write the settings to {:?}: {}", self.path, err);
+        }
+    }
+}
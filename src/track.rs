@@ -0,0 +1,204 @@
+use std::{
+    fmt,
+    error::Error,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+use serde_json::{
+    Value,
+    Map as JsonMap,
+};
+use serenity::model::id::UserId;
+use songbird::input::Metadata;
+
+// These are huge and nothing uses them, no point keeping them around for every song in a playlist
+const DROPPED_FIELDS: &[&str] = &[
+    "formats",
+    "requested_formats",
+    "thumbnails",
+    "subtitles",
+    "automatic_captions",
+    "http_headers",
+];
+
+#[derive(Clone, Debug)]
+pub struct Track{
+    pub title: String,
+    // the page the song came from, e.g. the youtube video page
+    pub webpage_url: Option<String>,
+    // the url ffmpeg actually reads the audio from
+    pub stream_url: String,
+    // None for anything that doesn't say how long it is, like a livestream
+    pub duration: Option<Duration>,
+    // a livestream or radio station, something with no end to it. Not knowing how long a song is
+    // doesn't make it live, plenty of plain files don't say
+    pub live: bool,
+    pub uploader: Option<String>,
+    pub thumbnail: Option<String>,
+    // which youtube-dl extractor handled the link (youtube, soundcloud, generic, ...)
+    pub extractor: Option<String>,
+    pub requester: UserId,
+    pub added_at: SystemTime,
+    // the rest of whatever youtube-dl told us about the song, in case something needs it later.
+    // Nothing reads it yet, it's kept on purpose so new features don't have to run youtube-dl again
+    #[allow(dead_code)]
+    pub extra: Option<JsonMap<String, Value>>,
+}
+
+#[derive(Debug)]
+pub enum TrackError{
+    Json(serde_json::Error),
+    NotAnObject,
+    MissingField(&'static str),
+}
+
+impl fmt::Display for TrackError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            TrackError::Json(err) => write!(f, "invalid json: {}", err),
+            TrackError::NotAnObject => write!(f, "expected a json object"),
+            TrackError::MissingField(field) => write!(f, "missing the '{}' field", field),
+        }
+    }
+}
+
+impl Error for TrackError{}
+
+impl From<serde_json::Error> for TrackError{
+    fn from(err: serde_json::Error) -> TrackError{
+        TrackError::Json(err)
+    }
+}
+
+fn get_string(map: &JsonMap<String, Value>, field: &str) -> Option<String>{
+    map.get(field).and_then(Value::as_str).map(str::to_owned)
+}
+
+impl Track{
+    // A bare track for sources that only give us a name and somewhere to read the audio from
+    pub fn new(title: String, stream_url: String, requester: UserId) -> Track{
+        Track{
+            title: title,
+            webpage_url: None,
+            stream_url: stream_url,
+            duration: None,
+            live: false,
+            uploader: None,
+            thumbnail: None,
+            extractor: None,
+            requester: requester,
+            added_at: SystemTime::now(),
+            extra: None,
+        }
+    }
+
+    // Builds a track out of one line of youtube-dl's --print-json output
+    pub fn from_ytdl_json(data: &str, requester: UserId) -> Result<Track, TrackError>{
+        let value: Value = serde_json::from_str(data)?;
+        let mut map = match value{
+            Value::Object(map) => map,
+            _ => return Err(TrackError::NotAnObject),
+        };
+
+        // the stream url is the only thing we can't play without
+        let stream_url = get_string(&map, "url").ok_or(TrackError::MissingField("url"))?;
+        let webpage_url = get_string(&map, "webpage_url");
+        let title = get_string(&map, "title")
+            .or_else(|| get_string(&map, "fulltitle"))
+            .or_else(|| webpage_url.clone())
+            .unwrap_or_else(|| "Unknown title".to_owned());
+        // yt-dlp hands back a float here, youtube-dl an integer, as_f64 takes both
+        let duration = map.get("duration")
+            .and_then(Value::as_f64)
+            .filter(|secs| secs.is_finite() && *secs > 0.0)
+            .map(Duration::from_secs_f64);
+
+        let live = map.get("is_live").and_then(Value::as_bool).unwrap_or(false);
+
+        for field in DROPPED_FIELDS{
+            map.remove(*field);
+        }
+
+        Ok(Track{
+            title: title,
+            webpage_url: webpage_url,
+            stream_url: stream_url,
+            duration: duration,
+            live: live,
+            uploader: get_string(&map, "uploader"),
+            thumbnail: get_string(&map, "thumbnail"),
+            extractor: get_string(&map, "extractor"),
+            requester: requester,
+            added_at: SystemTime::now(),
+            extra: Some(map),
+        })
+    }
+
+    // How long it is for lists of songs, a livestream says so and a file that never said how long it
+    // is just gets question marks
+    pub fn length_text(&self) -> String{
+        match self.duration{
+            _ if self.live => "LIVE".to_owned(),
+            Some(dur) => format_duration(dur),
+            None => "?:??".to_owned(),
+        }
+    }
+
+    // The metadata songbird wants alongside the audio
+    pub fn metadata(&self) -> Metadata{
+        Metadata{
+            title: Some(self.title.clone()),
+            artist: self.uploader.clone(),
+            duration: self.duration,
+            source_url: self.webpage_url.clone(),
+            thumbnail: self.thumbnail.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+// 83 seconds comes out as "1:23", an hour or more gets the hours on the front too
+pub fn format_duration(dur: Duration) -> String{
+    let secs = dur.as_secs();
+    if secs >= 3600{
+        format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+    }else{
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+// The other way around, "1:23" or "83" both give back 83 seconds and "1:02:03" works for hours
+pub fn parse_duration(text: &str) -> Option<Duration>{
+    let mut secs: u64 = 0;
+    let parts = text.trim().split(':').collect::<Vec<&str>>();
+    if parts.len() > 3{
+        return None;
+    }
+    for part in parts{
+        // anything too big to add up is no good either
+        secs = secs.checked_mul(60)?.checked_add(part.parse::<u64>().ok()?)?;
+    }
+    Some(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn durations_both_ways(){
+        assert_eq!(parse_duration("83"), Some(Duration::from_secs(83)));
+        assert_eq!(parse_duration("1:23"), Some(Duration::from_secs(83)));
+        assert_eq!(parse_duration(" 1:02:03 "), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_duration("1:2:3:4"), None);
+        assert_eq!(parse_duration("1:xx"), None);
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("99999999999999999:0"), None);
+        assert_eq!(parse_duration("18446744073709551615"), Some(Duration::from_secs(u64::MAX)));
+        assert_eq!(format_duration(Duration::from_secs(83)), "1:23");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+    }
+}